@@ -1,17 +1,34 @@
 const PROGRAM_NAME: &str = "fqView";
+const MOUSE_BACK: i32 = 4; //side buttons of the mouse
+const MOUSE_FORWARD: i32 = 5;
 
 use std::path::PathBuf;
 
+use image::DynamicImage;
+
 mod my_app;
 mod my_view;
 mod my_display;
 mod my_browser;
+mod my_comic;
 mod my_menu;
 mod my_model;
+mod my_slideshow;
+mod my_raw;
+mod my_layers;
+mod my_histogram;
+mod my_compare;
+mod my_tiff;
+mod my_export;
+mod my_save;
+mod my_fileops;
+mod my_marks;
+mod my_xmp;
+mod my_duplicates;
+mod my_rename;
+mod my_history;
 
-use image::DynamicImage;
-
-use crate::my_app::MyApp;
+use crate::{my_app::MyApp, my_compare::CompareStats, my_display::MipImages, my_duplicates::DuplicateGroup, my_export::Metadata, my_histogram::Histogram, my_marks::{Mark, MarkChange, MarkFilter}};
 
 pub fn run(args: Vec<String>) {
     if let Ok(mut app) = MyApp::build(args) {
@@ -26,10 +43,22 @@ pub enum Message {
     Quit,
     ToggleFullscreen,
     About,
+    ShowComicInfo,
     ShowListing(Vec<Listing>, PathBuf),
     OpenItem(i32),
     UpDir(i32),
-    ImageDecoded(DynamicImage, PathBuf),
+    GotoPath(String), //typed in the path field
+    CompletePath(String),
+    GoBack, //to the previous listing, with its selection and image
+    GoForward,
+    ImageDecoded(MipImages, PathBuf),
+    MetadataRead(Metadata, PathBuf), //sent before the decoded image when the file has any
+    AnimationDecoded(Vec<(MipImages, f64)>, PathBuf), //frames and seconds to show each
+    VectorDecoded(MipImages, PathBuf, (f32, f32), f32), //raster, logical size and scale it was rasterized at
+    LayersDecoded(Vec<(String, MipImages)>, PathBuf), //named layers, the first is shown
+    NextLayer,
+    PrevLayer,
+    RasterizeVector(f32), //displayed vector image needs this scale to stay sharp
     WantToDisplay(PathBuf),
     ImageLoaded(PathBuf),
     StopImageDisplay,
@@ -41,6 +70,61 @@ pub enum Message {
     Zoom1to1,
     ZoomFitToWindow,
     ToggleKeepAR,
+    RotateLeft,
+    RotateRight,
+    FlipHorizontal,
+    FlipVertical,
+    SetSpreadMode(SpreadMode),
+    SetCompareMode(CompareMode),
+    SetZoomModifier(ZoomModifier),
+    CompareWithSelected, //the image selected in the browser
+    CompareWithNext,
+    CompareReady(MipImages, CompareStats, PathBuf, PathBuf), //difference image and numbers for the pair
+    ToggleFlicker,
+    ToggleFlickerTimer,
+    ToggleSlideshow,
+    SlideshowTick,
+    SlideshowPause,
+    SlideshowFaster,
+    SlideshowSlower,
+    SlideshowSetInterval,
+    SlideshowShuffle,
+    SlideshowLoop,
+    SlideshowPauseOnInput,
+    SlideshowCrossfade,
+    UserInput,
+    PixelAt(Option<(f32, f32)>), //normalized source coordinates under the mouse
+    CopyPixel,
+    CopyImage, //with its path and uri, the selected part when there is a selection
+    CopyReady(DynamicImage, PathBuf), //rgba pixels converted on a thread, put on the clipboard here
+    ToggleHistogram,
+    ToggleHistogramRegion,
+    HistogramReady(Histogram, PathBuf),
+    ToggleMinimap,
+    SelectionChanged(Option<(f32, f32, f32, f32)>), //normalized source rectangle while dragging
+    ExportSelection,
+    SaveAs,
+    TrashFile, //the displayed image or the selected browser entry
+    MoveFile,
+    CopyFile,
+    RenameFile,
+    BatchRename, //the entries selected in the browser
+    UndoFileOp,
+    SetMark(MarkChange), //the displayed image or the selected browser entry
+    SetMarkFilter(MarkFilter),
+    EditTags,
+    SearchTags,
+    EmbeddedMark(Mark, PathBuf), //from xmp in the image file, read while decoding
+    EmbeddedMarks(Vec<(PathBuf, Mark)>), //from xmp in the listed image files, read after listing
+    FindDuplicates, //below the current folder or in the current archive
+    DuplicateProgress(usize, usize), //images hashed and found
+    DuplicatesFound(Vec<DuplicateGroup>, PathBuf),
+    ReviewDuplicate, //a line was picked in the review window
+    TrashDuplicate,
+    KeepDuplicate, //trash the rest of the group
+    SkipDuplicates,
+    TrashRejects, //all rejected images in the current folder
+    MoveRejects,
     Info(String),
     UpdateStatusData,
     ZoomChanged,
@@ -71,6 +155,84 @@ pub struct ViewSettings {
     pub zoom_lvl_y: f32,
     pub centerpos_x: f32,
     pub centerpos_y: f32,
+    pub orientation: Orientation,
+}
+
+///how many pages are shown side by side, and in which order
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpreadMode {
+    Single,
+    LeftToRight,
+    RightToLeft, //manga
+}
+
+///held while turning the mouse wheel to zoom instead of changing image
+#[derive(Clone, Copy, PartialEq)]
+pub enum ZoomModifier {
+    Ctrl,
+    Shift,
+    Alt,
+}
+
+impl ZoomModifier {
+    pub fn is_held(&self) -> bool {
+        match self {
+            ZoomModifier::Ctrl => fltk::app::is_event_ctrl(),
+            ZoomModifier::Shift => fltk::app::is_event_shift(),
+            ZoomModifier::Alt => fltk::app::is_event_alt(),
+        }
+    }
+}
+
+///how the second image is shown next to the displayed one
+#[derive(Clone, Copy, PartialEq)]
+pub enum CompareMode {
+    Off,
+    SideBySide, //split viewports sharing zoom and position
+    Difference, //amplified absolute difference
+    Wipe, //first image left of a draggable line, second image right of it
+    Flicker, //one image at a time, switched by key or timer
+}
+
+///view transform, flips are applied after rotating so they always follow the screen axes
+#[derive(Clone, Copy, Default, PartialEq)]
+pub struct Orientation {
+    pub quarter_turns: u8, //clockwise
+    pub flip_h: bool,
+    pub flip_v: bool,
+}
+
+impl Orientation {
+    pub fn rotate_right(&mut self) {
+        if self.flip_h != self.flip_v { //a single flip mirrors the direction of the turn
+            self.quarter_turns = (self.quarter_turns + 3) % 4;
+        } else {
+            self.quarter_turns = (self.quarter_turns + 1) % 4;
+        }
+    }
+
+    pub fn rotate_left(&mut self) {
+        if self.flip_h != self.flip_v {
+            self.quarter_turns = (self.quarter_turns + 1) % 4;
+        } else {
+            self.quarter_turns = (self.quarter_turns + 3) % 4;
+        }
+    }
+
+    ///width and height of the image are swapped on screen
+    pub fn is_transposed(&self) -> bool {
+        self.quarter_turns % 2 == 1
+    }
+
+    ///maps normalized coordinates on screen to normalized coordinates in the source image
+    pub fn to_source(&self, x: f32, y: f32) -> (f32, f32) {
+        let mut x = if self.flip_h { 1. - x } else { x };
+        let mut y = if self.flip_v { 1. - y } else { y };
+        for _ in 0..self.quarter_turns {
+            (x, y) = (y, 1. - x); //undo one clockwise turn
+        }
+        (x, y)
+    }
 }
 
 pub fn screen_center() -> (i32, i32) {
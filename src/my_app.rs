@@ -1,9 +1,12 @@
-use std::{error::Error, io, path::PathBuf};
+use std::{error::Error, path::{Path, PathBuf}, sync::Arc};
 
 use fltk::{app::{self, Receiver, Sender}, prelude::WidgetExt};
 
 
-use crate::{my_model::MyModel, my_view::MyView, screen_center, Message, PROGRAM_NAME};
+use clipboard_rs::{common::{RustImage, RustImageData}, Clipboard, ClipboardContent, ClipboardContext};
+use image::{DynamicImage, GenericImageView};
+
+use crate::{my_compare, my_export::{self, ExportFormat, ExportOptions}, my_fileops::{self, FileOp}, my_history::{History, Visit}, my_marks::{Mark, MarkChange, MarkFilter}, my_rename, my_save, my_display::{Animation, MipChain, MipImages}, my_duplicates::{self, DuplicateReview}, my_histogram::Histogram, my_model::MyModel, my_slideshow::Slideshow, my_view::MyView, screen_center, CompareMode, Message, SpreadMode, PROGRAM_NAME};
 
 pub struct Stats {
     statusbar: bool,
@@ -18,6 +21,26 @@ pub struct MyApp {
     view: MyView,
     model: MyModel,
     start_path: PathBuf,
+    slideshow: Slideshow,
+    compare_with: Option<PathBuf>, //picked in the browser, the next image is used without it
+    partner_source: Option<(PathBuf, Arc<DynamicImage>)>, //pixels of the compared image
+    partner_requested: Option<PathBuf>,
+    compared: Option<(PathBuf, PathBuf)>, //pair the difference and numbers are for, or being computed for
+    difference_size: usize, //bytes of the difference texture
+    source: Option<(PathBuf, Arc<DynamicImage>)>, //pixels of the displayed image, for the pixel inspector, shared with the worker threads
+    source_requested: Option<PathBuf>, //decoded again to get the pixels
+    pixel_hex: Option<String>, //color under the mouse
+    pending_export: Option<(PathBuf, PathBuf, ExportOptions)>, //image, destination and options, waiting for the pixels
+    pending_copy: Option<PathBuf>, //image to put on the clipboard once its pixels are at hand
+    clipboard: Option<ClipboardContext>, //kept alive, on linux the copied data is served from it
+    file_ops: Vec<FileOp>, //done file changes, the last is undone first
+    duplicates: Option<DuplicateReview>, //groups left to review
+    finding_duplicates: bool,
+    history: History, //visited listings
+    histogram_shown: bool,
+    histogram_region: bool, //only the visible part of the image
+    histogram_busy: bool, //computing on a thread
+    histogram_dirty: bool, //the view changed while computing
     gpu_data_size: usize,
     image_cache_size: usize,
 }
@@ -53,6 +76,26 @@ impl MyApp {
             view,
             model,
             start_path,
+            slideshow: Slideshow::new(tx),
+            compare_with: None,
+            partner_source: None,
+            partner_requested: None,
+            compared: None,
+            difference_size: 0,
+            source: None,
+            source_requested: None,
+            pixel_hex: None,
+            pending_export: None,
+            pending_copy: None,
+            clipboard: None,
+            file_ops: Vec::new(),
+            duplicates: None,
+            finding_duplicates: false,
+            history: History::default(),
+            histogram_shown: false,
+            histogram_region: false,
+            histogram_busy: false,
+            histogram_dirty: false,
             gpu_data_size: 0,
             image_cache_size: 0,
         })
@@ -72,6 +115,10 @@ impl MyApp {
                         let y = self.view.display.s_vc.borrow().get_y_zoom();
                         let s = format!("Zoom: {:.2} x {:.2}", x, y);
                         self.view.set_stat_zoomlvl(&s);
+                        self.view.update_scrollbars();
+                        if self.histogram_region {
+                            self.request_histogram();
+                        }
                     }
 
                     UpdateStatusData => {
@@ -96,89 +143,238 @@ impl MyApp {
                     }
 
                     ShowListing(listing, pb) => {
-                        self.view.populate_browser(&listing);
+                        self.view.populate_browser(&listing, &self.model.marks);
+                        if self.slideshow.is_running() {
+                            self.slideshow.set_images(self.model.get_image_list(), self.view.want_to_display.as_ref());
+                        }
                         self.view.set_input_text(pb);
                         self.view.set_browsing_layout();
                         self.tx.send(UpdateStatusData);
                     }
 
                     About => fltk::dialog::message(screen_center().0 - 300, screen_center().1 - 100,
-                    format!("{PROGRAM_NAME} is a simple image viewer and could not be written without these:\n\n\tfltk-rs by Mohammed Alyousef\n\tarchive-reader\n\timage crate\n\tlibarchive\n\tSpeedy2D\n\tzip crate\n\tlibheif
+                    format!("{PROGRAM_NAME} is a simple image viewer and could not be written without these:\n\n\tfltk-rs by Mohammed Alyousef\n\tarchive-reader\n\timage crate\n\tlibarchive\n\tSpeedy2D\n\tzip crate\n\tlibheif\n\tjxl-oxide\n\tresvg\n\tpsd crate\n\texr crate\n\twebp crate\n\tclipboard-rs\n\ttrash crate
                             \n{PROGRAM_NAME} is Copyright 2025 by Allan Pedersen
                             \nPermission is hereby granted, free of charge, to any person obtaining a copy of this software\nand associated documentation files (the “Software”), to deal in the Software without\nrestriction, including without limitation the rights to use, copy, modify, merge, publish,\ndistribute, sublicense, and/or sell copies of the Software, and to permit persons to whom the\nSoftware is furnished to do so, subject to the following conditions:
                             \nThe above copyright notice and this permission notice shall be included in all copies or\nsubstantial portions of the Software.
                             \nTHE SOFTWARE IS PROVIDED “AS IS”, WITHOUT WARRANTY OF ANY KIND, EXPRESS OR\nIMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,\nFITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL\nTHE AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR\nOTHER LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE,\nARISING FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR\nOTHER DEALINGS IN THE SOFTWARE.
                     ").as_str()), //make this include_bytes somehow
                     
+                    ShowComicInfo => {
+                        let path = self.view.want_to_display.clone().unwrap_or(self.model.get_cwd());
+                        let text = match self.model.get_comic_info(&path) {
+                            Some(info) => info.details(),
+                            None => String::from("No ComicInfo.xml found for this archive."),
+                        };
+                        fltk::dialog::message(screen_center().0 - 200, screen_center().1 - 100, &text);
+                    },
+
                     Quit => {
                         println!("Graceful exit, goodbye.");
                         self.app.quit();
                     },
 
                     OpenItem(item_nr) => {
-                        let res = self.model.open_item(item_nr);
-                        if res.is_err() {
-                            let e = res.unwrap_err();
-                            self.view.set_error_message(e);
-                        }
+                        self.navigate(|model| model.open_item(item_nr));
                     },
 
                     UpDir(_item_nr) => { //use item_nr when remembering last selection
-                        self.model.goto_parent();
+                        self.navigate(|model| model.goto_parent());
                     },
 
-                    ImageDecoded(image, image_pb) => {
+                    GotoPath(text) => {
+                        let path = self.model.typed_path(&text);
+                        self.navigate(|model| model.goto_path(&path));
+                    },
+
+                    GoBack => self.go_history(false),
+                    GoForward => self.go_history(true),
+
+                    CompletePath(text) => {
+                        let (completed, names) = self.model.complete_path(&text);
+                        self.view.set_input_completion(&completed);
+                        match names.len() {
+                            0 => self.view.set_stat_message("No folder or archive starts with that"),
+                            1 => self.view.set_stat_message(""),
+                            _ => self.view.set_stat_message(&names.join("  ")),
+                        }
+                    },
+
+                    ImageDecoded(images, image_pb) => {
                         self.model.trying_to_load.remove(&image_pb);
                         if Some(image_pb.clone()) == self.view.want_to_display {
-                            let img_size = image.as_bytes().len();
-                            let res = self.view.display.s_vc.borrow_mut().upload_image(image);
+                            if self.model.texture_cache.contains_key(&image_pb) { //decoded again for its pixels
+                                self.source = Some((image_pb, images.image));
+                                self.request_histogram();
+                                self.request_compare();
+                                self.run_export();
+                                self.run_copy();
+                            } else if let Some(handle) = self.upload(image_pb.clone(), &images) {
+                                self.source = Some((image_pb.clone(), images.image));
+                                self.show(&image_pb, handle);
+                            }
+                        } else if Some(image_pb.clone()) == self.view.partner {
+                            if !self.model.texture_cache.contains_key(&image_pb) {
+                                if let Some(handle) = self.upload(image_pb.clone(), &images) {
+                                    self.view.display.set_second_image(Some(handle));
+                                }
+                            }
+                            if self.view.display.compare_mode() != CompareMode::Off {
+                                self.partner_source = Some((image_pb, images.image));
+                                self.request_compare();
+                            }
+                        } else {
+                            self.image_cache_size += images.data_size();
+                            self.model.image_cache.insert(image_pb, images);
+                            //store unshowed images in gpu or ram?
+                        }
+                        self.tx.send(UpdateStatusData);
+                    },
+
+                    AnimationDecoded(mut frames, image_pb) => {
+                        self.model.trying_to_load.remove(&image_pb);
+                        let displayed = Some(image_pb.clone()) == self.view.want_to_display;
+                        let is_partner = Some(image_pb.clone()) == self.view.partner;
+                        if (displayed || is_partner) && self.model.animation_cache.contains_key(&image_pb) {
+                            //decoded again for its pixels, the first frame is used
+                            if !frames.is_empty() {
+                                let first = frames.swap_remove(0).0.image;
+                                if displayed {
+                                    self.source = Some((image_pb, first));
+                                    self.request_histogram();
+                                    self.run_export();
+                                    self.run_copy();
+                                } else {
+                                    self.partner_source = Some((image_pb, first));
+                                }
+                                self.request_compare();
+                            }
+                            continue;
+                        }
+
+                        let mut chains = Vec::new();
+                        let mut delays = Vec::new();
+                        for (frame, delay) in &frames {
+                            let res = self.view.display.s_vc.borrow_mut().upload_image(frame);
                             match res {
                                 Ok(handle) => {
-                                    self.gpu_data_size += img_size;
-                                    self.view.display_image(handle.clone());
-                                    self.model.texture_cache.insert(image_pb, handle);
+                                    self.gpu_data_size += handle.data_size();
+                                    chains.push(handle);
+                                    delays.push(*delay);
                                 },
 
                                 Err(e) => {
                                     self.view.set_error_message(e);
+                                    break;
                                 },
                             }
-                        } else {
-                            self.image_cache_size += image.as_bytes().len();
-                            self.model.image_cache.insert(image_pb, image);
-                            //store unshowed images in gpu or ram?
+                        }
+
+                        if !chains.is_empty() {
+                            let animation = Animation::new(chains, delays);
+                            let first = animation.first_frame().clone();
+                            self.model.texture_cache.insert(image_pb.clone(), first.clone());
+                            self.model.animation_cache.insert(image_pb.clone(), animation);
+                            if displayed {
+                                self.source = Some((image_pb.clone(), frames.swap_remove(0).0.image));
+                                self.show(&image_pb, first);
+                            } else if Some(image_pb.clone()) == self.view.partner {
+                                self.view.display.set_second_image(Some(first));
+                            }
                         }
                         self.tx.send(UpdateStatusData);
                     },
 
-                    WantToDisplay(image_pb) => {
-                        self.view.want_to_display = Some(image_pb.clone());
-                        if self.model.texture_cache.contains_key(&image_pb) {
-                            if let Some(handle) = self.model.texture_cache.get(&image_pb) {
-                                self.view.display_image(handle.clone());
-                            }
-                        } else if self.model.image_cache.contains_key(&image_pb) {
-                            if let Some(image) = self.model.image_cache.remove(&image_pb) {
-                                let img_size= image.as_bytes().len();                                
-                                let res = self.view.display.s_vc.borrow_mut().upload_image(image);
-                                match res {
-                                    Ok(handle) => {
-                                        self.gpu_data_size += img_size;
-                                        self.image_cache_size -= img_size;
-                                        self.view.display_image(handle.clone());
-                                        self.model.texture_cache.insert(image_pb, handle);
-                                    },
+                    VectorDecoded(images, image_pb, logical_size, scale) => {
+                        self.model.trying_to_load.remove(&image_pb);
+                        let res = self.view.display.s_vc.borrow_mut().upload_image(&images);
+                        match res {
+                            Ok(handle) => {
+                                let handle = handle.with_vector_size(logical_size, scale);
+                                self.gpu_data_size += handle.data_size();
+                                let old = self.model.texture_cache.insert(image_pb.clone(), handle.clone());
+                                if let Some(old) = &old {
+                                    self.gpu_data_size -= old.data_size();
+                                }
 
-                                    Err(e) => {
-                                        self.view.set_error_message(e);
-                                    },
+                                if Some(image_pb.clone()) == self.view.want_to_display {
+                                    self.source = Some((image_pb.clone(), images.image));
+                                    if old.is_some() { //sharper version of what is shown
+                                        self.view.display.replace_image(handle);
+                                    } else {
+                                        self.show(&image_pb, handle);
+                                    }
+                                } else if Some(image_pb.clone()) == self.view.partner {
+                                    self.view.display.set_second_image(Some(handle));
                                 }
+                            },
+
+                            Err(e) => {
+                                self.view.set_error_message(e);
+                            },
+                        }
+                        self.tx.send(UpdateStatusData);
+                    },
+
+                    LayersDecoded(mut layers, image_pb) => {
+                        if layers.len() > 1 {
+                            //read again after leaving the image, the layer that was shown is kept
+                            let index = self.model.layer_index.get(&image_pb).copied().unwrap_or(0).min(layers.len() - 1);
+                            let shown = layers[index].1.clone();
+                            self.drop_layers(&image_pb);
+                            self.model.layer_index.insert(image_pb.clone(), index);
+                            if Some(image_pb.clone()) == self.view.want_to_display { //preloaded images only keep the shown layer
+                                self.image_cache_size += layers.iter().map(|(_, images)| images.data_size()).sum::<usize>();
+                                self.model.layers.insert(image_pb.clone(), layers);
+                                self.show_layer_name(&image_pb);
                             }
+                            self.tx.send(ImageDecoded(shown, image_pb));
+                        } else if let Some((_, images)) = layers.pop() {
+                            self.tx.send(ImageDecoded(images, image_pb));
+                        }
+                    },
+
+                    NextLayer => self.cycle_layer(1),
+                    PrevLayer => self.cycle_layer(-1),
+
+                    RasterizeVector(scale) => {
+                        if let Some(image_pb) = self.view.want_to_display.clone() {
+                            self.model.rasterize_vector(image_pb, scale);
+                        }
+                    },
+
+                    WantToDisplay(image_pb) => {
+                        if let Some(left) = self.view.want_to_display.replace(image_pb.clone()).filter(|pb| *pb != image_pb) {
+                            self.drop_layers(&left);
+                        }
+                        if self.source_requested.as_ref() != Some(&image_pb) {
+                            self.source_requested = None;
+                        }
+                        if self.source.as_ref().is_some_and(|(pb, _)| *pb != image_pb) {
+                            self.source = None;
+                        }
+                        if self.pending_export.as_ref().is_some_and(|(pb, _, _)| *pb != image_pb) {
+                            self.pending_export = None;
+                        }
+                        if self.pending_copy.as_ref().is_some_and(|pb| *pb != image_pb) {
+                            self.pending_copy = None;
+                        }
+                        self.view.comic_label = self.model.get_comic_info(&image_pb).and_then(|info| info.label());
+                        self.view.set_stat_mark(&self.model.get_mark(&image_pb));
+                        self.show_layer_name(&image_pb);
+                        if let Some(handle) = self.get_texture(&image_pb) {
+                            self.show(&image_pb, handle);
                         } else {
                             self.model.load_image_data(image_pb);
                         }
+                        self.update_partner();
                         
-                        if let Some(current) = self.view.want_to_display.clone() {
+                        let preload_from = if self.view.display.compare_mode() == CompareMode::Off {
+                            self.view.partner.clone().or(self.view.want_to_display.clone())
+                        } else {
+                            self.view.want_to_display.clone()
+                        };
+                        if let Some(current) = preload_from {
                             if let Some((next, _)) = self.model.get_next_image(current) {
                                 self.model.load_image_data(next);                                
                             }
@@ -186,8 +382,82 @@ impl MyApp {
                         self.tx.send(UpdateStatusData);
                     },
 
+                    SetSpreadMode(mode) => {
+                        self.view.display.set_spread_mode(mode);
+                        self.update_partner();
+                    },
+
+                    SetZoomModifier(modifier) => self.view.display.set_zoom_modifier(modifier),
+
+                    SetCompareMode(mode) => {
+                        self.view.display.set_compare_mode(mode);
+                        if mode != CompareMode::Flicker {
+                            self.view.display.set_flicker_timer(false);
+                        }
+                        self.update_partner();
+                    },
+
+                    CompareReady(difference, stats, a, b) => {
+                        let current = (Some(a.clone()), Some(b.clone())) == (self.view.want_to_display.clone(), self.view.partner.clone());
+                        if current {
+                            let res = self.view.display.s_vc.borrow_mut().upload_image(&difference);
+                            match res {
+                                Ok(handle) => {
+                                    self.gpu_data_size -= self.difference_size;
+                                    self.difference_size = handle.data_size();
+                                    self.gpu_data_size += self.difference_size;
+                                    self.view.display.set_difference(Some(handle));
+                                },
+
+                                Err(e) => self.view.set_error_message(e),
+                            }
+                            self.view.set_stat_message(&stats.summary());
+                            self.tx.send(UpdateStatusData);
+                        } //else the images changed while computing and the new pair is already requested
+                    },
+
+                    ToggleFlicker => {
+                        if self.view.display.compare_mode() == CompareMode::Flicker {
+                            let second = self.view.display.flicker();
+                            let shown = if second { self.view.partner.clone() } else { self.view.want_to_display.clone() };
+                            if let Some(name) = shown.as_ref().and_then(|pb| pb.file_name()) {
+                                self.view.set_stat_message(&format!("Showing {}", name.to_string_lossy()));
+                            }
+                        }
+                    },
+
+                    ToggleFlickerTimer => {
+                        let running = !self.view.display.is_flicker_timer_running();
+                        self.view.display.set_flicker_timer(running && self.view.display.compare_mode() == CompareMode::Flicker);
+                    },
+
+                    CompareWithSelected => {
+                        match self.view.selected_browser_item().and_then(|nr| self.model.get_image_path(nr)) {
+                            Some(image_pb) => {
+                                let name = image_pb.file_name().unwrap_or_default().to_string_lossy().to_string();
+                                self.view.set_stat_message(&format!("Comparing with {name}"));
+                                self.compare_with = Some(image_pb);
+                                self.update_partner();
+                            },
+
+                            None => self.view.set_stat_message("Select an image in the browser to compare with"),
+                        }
+                    },
+
+                    CompareWithNext => {
+                        self.compare_with = None;
+                        self.update_partner();
+                    },
+
                     StopImageDisplay => {                        
                         self.view.stop_image_display();
+                        self.source = None;
+                        self.source_requested = None;
+                        self.pixel_hex = None;
+                        self.pending_export = None;
+                        self.pending_copy = None;
+                        self.view.display.set_histogram(None);
+                        self.view.set_stat_mark(&Mark::default());
                     },
 
                     ImageLoaded(image_pb) => {
@@ -198,7 +468,12 @@ impl MyApp {
                     },
 
                     NextImage => {
-                        if let Some(current) = self.view.want_to_display.clone() {
+                        if let Some(mut current) = self.view.want_to_display.clone() {
+                            if self.view.display.s_vc.borrow().showing_spread() { //step past the second page
+                                if let Some(partner) = self.view.partner.clone() {
+                                    current = partner;
+                                }
+                            }
                             if let Some((next, index)) = self.model.get_next_image(current) {
                                 self.tx.send(WantToDisplay(next));
                                 self.view.select_browser_item(index as i32);
@@ -208,7 +483,15 @@ impl MyApp {
 
                     PrevImage => {
                         if let Some(current) = self.view.want_to_display.clone() {
-                            if let Some((prev, index)) = self.model.get_prev_image(current) {
+                            if let Some((mut prev, mut index)) = self.model.get_prev_image(current) {
+                                let two_pages = self.view.display.spread_mode() != SpreadMode::Single && self.view.display.compare_mode() == CompareMode::Off;
+                                if two_pages && !self.is_wide(&prev) {
+                                    if let Some((prev2, index2)) = self.model.get_prev_image(prev.clone()) {
+                                        if !self.is_wide(&prev2) {
+                                            (prev, index) = (prev2, index2);
+                                        }
+                                    }
+                                }
                                 self.tx.send(WantToDisplay(prev));
                                 self.view.select_browser_item(index as i32);
                             }
@@ -236,6 +519,402 @@ impl MyApp {
                         self.view.display.glut_win.redraw();
                     },
 
+                    RotateLeft => {
+                        self.view.display.s_vc.borrow_mut().rotate_left();
+                        self.view.display.glut_win.redraw();
+                    },
+
+                    RotateRight => {
+                        self.view.display.s_vc.borrow_mut().rotate_right();
+                        self.view.display.glut_win.redraw();
+                    },
+
+                    FlipHorizontal => {
+                        self.view.display.s_vc.borrow_mut().flip_horizontal();
+                        self.view.display.glut_win.redraw();
+                    },
+
+                    FlipVertical => {
+                        self.view.display.s_vc.borrow_mut().flip_vertical();
+                        self.view.display.glut_win.redraw();
+                    },
+
+                    ToggleSlideshow => {
+                        if self.slideshow.is_running() {
+                            self.slideshow.stop();
+                            self.view.set_stat_message("Slideshow stopped");
+                        } else {
+                            let images = self.model.get_image_list();
+                            if self.view.want_to_display.is_none() {
+                                if let Some(first) = images.first() {
+                                    self.tx.send(WantToDisplay(first.clone()));
+                                }
+                            }
+                            if !images.is_empty() {
+                                let current = self.view.want_to_display.clone().or_else(|| images.first().cloned());
+                                self.slideshow.start(images, current.as_ref());
+                                self.view.set_stat_message(&format!("Slideshow, {} s per image", self.slideshow.interval));
+                            }
+                        }
+                    },
+
+                    SlideshowTick => {
+                        if self.slideshow.is_running() {
+                            let next = self.get_slideshow_next();
+                            match next {
+                                Some(next) => {
+                                    self.view.crossfade_next = self.slideshow.crossfade;
+                                    if let Some(index) = self.model.get_index(&next) {
+                                        self.view.select_browser_item(index as i32);
+                                    }
+                                    self.tx.send(WantToDisplay(next));
+                                    self.slideshow.arm();
+                                },
+
+                                None => {
+                                    self.slideshow.stop();
+                                    self.view.set_stat_message("Slideshow finished");
+                                },
+                            }
+                        }
+                    },
+
+                    SlideshowPause => {
+                        if self.slideshow.is_paused() {
+                            self.slideshow.resume();
+                            self.view.set_stat_message("Slideshow continued");
+                        } else if self.slideshow.is_running() {
+                            self.slideshow.pause();
+                            self.view.set_stat_message("Slideshow paused, space to continue");
+                        }
+                    },
+
+                    UserInput => {
+                        if self.slideshow.pause_on_input && self.slideshow.is_running() && !self.slideshow.is_paused() {
+                            self.slideshow.pause();
+                            self.view.set_stat_message("Slideshow paused, space to continue");
+                        }
+                    },
+
+                    SlideshowFaster => {
+                        self.slideshow.faster();
+                        self.view.set_stat_message(&format!("Slideshow, {} s per image", self.slideshow.interval));
+                    },
+
+                    SlideshowSlower => {
+                        self.slideshow.slower();
+                        self.view.set_stat_message(&format!("Slideshow, {} s per image", self.slideshow.interval));
+                    },
+
+                    SlideshowSetInterval => {
+                        let current = self.slideshow.interval.to_string();
+                        if let Some(input) = fltk::dialog::input(screen_center().0 - 150, screen_center().1 - 50, "Seconds per image:", &current) {
+                            match input.trim().parse::<f64>() {
+                                Ok(interval) => self.slideshow.set_interval(interval),
+                                Err(_) => self.view.set_stat_message("Interval must be a number of seconds"),
+                            }
+                        }
+                    },
+
+                    SlideshowShuffle => {
+                        self.slideshow.shuffle = !self.slideshow.shuffle;
+                        if self.slideshow.is_running() {
+                            self.slideshow.set_images(self.model.get_image_list(), self.view.want_to_display.as_ref());
+                        }
+                    },
+                    SlideshowLoop => self.slideshow.looping = !self.slideshow.looping,
+                    SlideshowPauseOnInput => self.slideshow.pause_on_input = !self.slideshow.pause_on_input,
+                    SlideshowCrossfade => self.slideshow.crossfade = !self.slideshow.crossfade,
+
+                    PixelAt(point) => self.inspect_pixel(point),
+
+                    CopyPixel => {
+                        if let Some(hex) = &self.pixel_hex {
+                            app::copy(hex);
+                            self.view.set_stat_message(&format!("Copied {hex}"));
+                        }
+                    },
+
+                    CopyImage => {
+                        match self.view.want_to_display.clone() {
+                            Some(image_pb) => {
+                                self.pending_copy = Some(image_pb);
+                                self.run_copy();
+                            },
+
+                            None => self.view.set_stat_message("Open an image to copy it"),
+                        }
+                    },
+
+                    CopyReady(pixels, image_pb) => {
+                        let (w, h) = pixels.dimensions();
+                        match self.copy_to_clipboard(pixels, &image_pb) {
+                            Ok(()) => self.view.set_stat_message(&format!("Copied {w} x {h} image and {}", image_pb.display())),
+                            Err(e) => self.view.set_error_message(e),
+                        }
+                    },
+
+                    TrashFile => {
+                        if let Some(path) = self.file_op_target() {
+                            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                            let question = format!("Move {name} to the trash?");
+                            if fltk::dialog::choice2(screen_center().0 - 150, screen_center().1 - 50, &question, "Cancel", "Move to trash", "") == Some(1) {
+                                let res = my_fileops::trash(&path);
+                                self.finish_file_op(res);
+                            }
+                        }
+                    },
+
+                    MoveFile => self.move_or_copy_file(false),
+                    CopyFile => self.move_or_copy_file(true),
+
+                    RenameFile => {
+                        if let Some(path) = self.file_op_target() {
+                            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                            if let Some(new_name) = fltk::dialog::input(screen_center().0 - 150, screen_center().1 - 50, "New name:", &name) {
+                                let res = my_fileops::rename(&path, &new_name);
+                                self.finish_file_op(res);
+                            }
+                        }
+                    },
+
+                    BatchRename => {
+                        let paths: Vec<PathBuf> = self.view.selected_browser_items().into_iter()
+                            .filter_map(|nr| self.model.get_item_path(nr))
+                            .collect();
+                        if paths.is_empty() {
+                            self.view.set_stat_message("Select the files to rename in the browser, Ctrl and Shift select several");
+                        } else if let Some(renames) = my_rename::ask_batch_rename(&paths) {
+                            let (done, errors) = my_fileops::rename_all(&renames);
+                            self.finish_batch(done, errors);
+                        }
+                    },
+
+                    UndoFileOp => {
+                        match self.file_ops.pop() {
+                            Some(mut op) => {
+                                let description = op.describe();
+                                let (affected, moves) = (op.affected(), op.moves());
+                                let res = my_fileops::undo(&mut op);
+                                //a failed batch keeps the part that wasn't undone
+                                let (still_affected, still_moved) = if res.is_ok() { (Vec::new(), Vec::new()) } else { (op.affected(), op.moves()) };
+                                let moved_back: Vec<(PathBuf, PathBuf)> = moves.into_iter()
+                                    .rev() //undone last first
+                                    .filter(|moved| !still_moved.contains(moved))
+                                    .map(|(from, to)| (to, from))
+                                    .collect();
+                                self.move_marks(&moved_back);
+                                let undone: Vec<PathBuf> = affected.into_iter().filter(|pb| !still_affected.contains(pb)).collect();
+                                self.after_file_change(&undone, &moved_back);
+                                match res {
+                                    Ok(()) => self.view.set_stat_message(&format!("Undone: {description}")),
+
+                                    Err(e) => {
+                                        self.file_ops.push(op); //still undoable once the problem is fixed
+                                        self.view.set_error_message(e);
+                                    },
+                                }
+                            },
+
+                            None => self.view.set_stat_message("Nothing to undo"),
+                        }
+                    },
+
+                    SetMark(change) => {
+                        if let Some(image_pb) = self.mark_target() {
+                            if let Err(e) = self.model.change_mark(&image_pb, change) {
+                                self.view.set_stat_message(&format!("Saving the mark failed: {e}"));
+                            }
+                            self.show_mark(&image_pb);
+                        }
+                    },
+
+                    EditTags => {
+                        if let Some(image_pb) = self.mark_target() {
+                            let tags = self.model.get_mark(&image_pb).tags_text();
+                            if let Some(text) = fltk::dialog::input(screen_center().0 - 150, screen_center().1 - 50, "Tags, separated by commas:", &tags) {
+                                self.tx.send(SetMark(MarkChange::Tags(Mark::parse_tags(&text))));
+                            }
+                        }
+                    },
+
+                    SearchTags => {
+                        if let Some(text) = fltk::dialog::input(screen_center().0 - 150, screen_center().1 - 50, "Show images with a tag or label containing:", "") {
+                            let text = text.trim().to_string();
+                            self.tx.send(SetMarkFilter(if text.is_empty() { MarkFilter::All } else { MarkFilter::Search(text) }));
+                        }
+                    },
+
+                    EmbeddedMark(mark, image_pb) => {
+                        if self.model.set_embedded_mark(&image_pb, mark) {
+                            self.show_mark(&image_pb);
+                        }
+                    },
+
+                    EmbeddedMarks(marks) => {
+                        let mut used = false;
+                        for (image_pb, mark) in marks {
+                            if self.model.set_embedded_mark(&image_pb, mark) {
+                                self.show_mark(&image_pb);
+                                used = true;
+                            }
+                        }
+                        let filter = self.model.get_mark_filter();
+                        if used && filter != MarkFilter::All {
+                            self.tx.send(SetMarkFilter(filter));
+                        }
+                    },
+
+                    SetMarkFilter(filter) => {
+                        self.view.check_mark_filter(&filter);
+                        let listing = self.model.set_mark_filter(filter);
+                        self.view.populate_browser(&listing, &self.model.marks);
+                        let shown = self.view.want_to_display.as_ref().and_then(|image_pb| self.model.get_index(image_pb));
+                        if let Some(index) = shown {
+                            self.view.select_browser_item(index as i32);
+                        } else if !listing.is_empty() {
+                            self.view.select_browser_item(0);
+                        }
+                    },
+
+                    FindDuplicates => {
+                        if self.finding_duplicates {
+                            self.view.set_stat_message("Already looking for duplicates");
+                        } else {
+                            self.finding_duplicates = true;
+                            let root = self.model.get_cwd();
+                            self.view.set_stat_message(&format!("Looking for duplicates in {}", root.display()));
+                            my_duplicates::find_duplicates(root, self.tx);
+                        }
+                    },
+
+                    DuplicateProgress(done, total) => {
+                        if self.finding_duplicates {
+                            self.view.set_stat_message(&format!("Looking for duplicates, {done} of {total} images"));
+                        }
+                    },
+
+                    DuplicatesFound(groups, root) => {
+                        self.finding_duplicates = false;
+                        if groups.is_empty() {
+                            self.view.set_stat_message(&format!("No duplicates in {}", root.display()));
+                        } else {
+                            let review = DuplicateReview::new(groups, self.tx);
+                            self.view.set_stat_message(&format!("{} groups with {} files", review.group_count(), review.file_count()));
+                            self.duplicates = Some(review);
+                        }
+                    },
+
+                    ReviewDuplicate => self.review_duplicate(),
+
+                    TrashDuplicate => {
+                        let selected = self.duplicates.as_ref().and_then(|review| {
+                            let (group, file) = review.selected()?;
+                            review.file(group, file).map(|candidate| candidate.path.clone())
+                        });
+                        if let Some(path) = selected {
+                            let res = my_fileops::trash(&path);
+                            let trashed = res.is_ok();
+                            self.finish_file_op(res);
+                            if trashed {
+                                if let Some(review) = self.duplicates.as_mut() {
+                                    review.remove_file(&path);
+                                }
+                                self.review_duplicate();
+                            }
+                        }
+                    },
+
+                    KeepDuplicate => {
+                        let selected = self.duplicates.as_ref().and_then(|review| {
+                            let (group, file) = review.selected()?;
+                            Some(my_duplicates::duplicates_of(review.group(group)?, file))
+                        });
+                        if let Some((others, differing)) = selected {
+                            let (done, errors) = my_fileops::batch(&others, my_fileops::trash);
+                            self.finish_batch(done, errors);
+                            let trashed: Vec<&PathBuf> = others.iter().filter(|path| path.symlink_metadata().is_err()).collect();
+                            if differing > 0 { //only alike through another file of the group
+                                self.view.set_stat_message(&format!("Trashed {} files, {differing} that differ too much from the kept one were left", trashed.len()));
+                            }
+                            if let Some(review) = self.duplicates.as_mut() {
+                                for path in trashed {
+                                    review.remove_file(path); //the group goes when only the kept file is left
+                                }
+                            }
+                            self.review_duplicate();
+                        }
+                    },
+
+                    SkipDuplicates => {
+                        if let Some(review) = self.duplicates.as_mut() {
+                            if let Some((group, _)) = review.selected() {
+                                review.remove_group(group);
+                            }
+                        }
+                        self.review_duplicate();
+                    },
+
+                    TrashRejects => self.apply_to_rejects(None),
+
+                    MoveRejects => {
+                        let cwd = self.model.get_cwd();
+                        if let Some(dir) = self.view.choose_folder("Move rejects to", &cwd) {
+                            self.apply_to_rejects(Some(dir));
+                        }
+                    },
+
+                    ToggleHistogram => {
+                        self.histogram_shown = !self.histogram_shown;
+                        if self.histogram_shown {
+                            self.request_histogram();
+                        } else {
+                            self.view.display.set_histogram(None);
+                        }
+                    },
+
+                    ToggleHistogramRegion => {
+                        self.histogram_region = !self.histogram_region;
+                        self.request_histogram();
+                    },
+
+                    SelectionChanged(rect) => {
+                        let size = self.view.want_to_display.as_ref()
+                            .and_then(|pb| self.model.texture_cache.get(pb))
+                            .map(|chain| chain.logical_size());
+                        match (rect, size) {
+                            (Some(rect), Some(size)) => {
+                                let (x, y, w, h) = my_export::pixel_rect(rect, size);
+                                self.view.set_stat_message(&format!("Selection {w} x {h} at {x}, {y}"));
+                            },
+                            _ => self.view.set_stat_message(""),
+                        }
+                    },
+
+                    ExportSelection => self.export_selection(),
+
+                    SaveAs => self.save_as(),
+
+                    MetadataRead(metadata, image_pb) => {
+                        self.model.metadata.insert(image_pb, metadata);
+                    },
+
+                    ToggleMinimap => {
+                        self.view.display.toggle_minimap();
+                    },
+
+                    HistogramReady(histogram, image_pb) => {
+                        self.histogram_busy = false;
+                        if self.histogram_shown && Some(image_pb) == self.view.want_to_display {
+                            self.view.set_stat_message(&histogram.summary());
+                            self.view.display.set_histogram(Some(histogram));
+                        }
+                        if self.histogram_dirty {
+                            self.histogram_dirty = false;
+                            self.request_histogram();
+                        }
+                    },
+
                     Info(s) => {
                         self.view.set_stat_message(&s);
                     }
@@ -244,5 +923,614 @@ impl MyApp {
         }
     }
 
+    ///displays the texture and starts the animation if the image has more frames
+    fn show(&mut self, image_pb: &PathBuf, handle: MipChain) {
+        self.view.display_image(handle);
+        self.view.display.set_animation(self.model.animation_cache.get(image_pb).cloned());
+        self.request_histogram();
+        self.request_compare();
+    }
+
+    ///uploads the image and moves it to the texture cache
+    fn upload(&mut self, image_pb: PathBuf, images: &MipImages) -> Option<MipChain> {
+        let res = self.view.display.s_vc.borrow_mut().upload_image(images);
+        match res {
+            Ok(handle) => {
+                self.gpu_data_size += handle.data_size();
+                self.model.texture_cache.insert(image_pb, handle.clone());
+                Some(handle)
+            },
+
+            Err(e) => {
+                self.view.set_error_message(e);
+                None
+            },
+        }
+    }
+
+    ///gets the texture for an image if it is on the gpu or decoded in ram
+    fn get_texture(&mut self, image_pb: &PathBuf) -> Option<MipChain> {
+        if let Some(handle) = self.model.texture_cache.get(image_pb) {
+            return Some(handle.clone());
+        }
+        if let Some(images) = self.model.image_cache.remove(image_pb) {
+            self.image_cache_size -= images.data_size();
+            let handle = self.upload(image_pb.clone(), &images);
+            if Some(image_pb) == self.view.want_to_display.as_ref() {
+                self.source = Some((image_pb.clone(), images.image));
+            } else if Some(image_pb) == self.view.partner.as_ref() && self.view.display.compare_mode() != CompareMode::Off {
+                self.partner_source = Some((image_pb.clone(), images.image));
+            }
+            return handle;
+        }
+        None
+    }
+
+    ///shows the next or previous layer in place of the current one, keeping zoom and position
+    fn cycle_layer(&mut self, step: isize) {
+        let Some(image_pb) = self.view.want_to_display.clone() else { return };
+        let Some(layers) = self.model.layers.get(&image_pb) else {
+            if self.model.layer_index.contains_key(&image_pb) { //left earlier, so the layers were dropped
+                self.model.reload_image_data(image_pb);
+                self.view.set_stat_message("Reading the layers again");
+            } else {
+                self.view.set_stat_message("No layers in this image");
+            }
+            return;
+        };
+
+        let count = layers.len() as isize;
+        let current = self.model.layer_index.get(&image_pb).copied().unwrap_or(0) as isize;
+        let index = (current + step).rem_euclid(count) as usize;
+        let images = layers[index].1.clone();
+        self.model.layer_index.insert(image_pb.clone(), index);
+
+        if let Some(old) = self.model.texture_cache.remove(&image_pb) {
+            self.gpu_data_size -= old.data_size();
+        }
+        if let Some(handle) = self.upload(image_pb.clone(), &images) {
+            self.view.display.replace_image(handle);
+        }
+        self.show_layer_name(&image_pb);
+        self.request_histogram();
+        self.tx.send(Message::UpdateStatusData);
+    }
+
+    ///only the shown layer of an image that isn't displayed is kept, as a texture
+    fn drop_layers(&mut self, image_pb: &PathBuf) {
+        if let Some(layers) = self.model.layers.remove(image_pb) {
+            self.image_cache_size -= layers.iter().map(|(_, images)| images.data_size()).sum::<usize>();
+        }
+    }
+
+    fn show_layer_name(&mut self, image_pb: &PathBuf) {
+        if let Some(layers) = self.model.layers.get(image_pb) {
+            let index = self.model.layer_index.get(image_pb).copied().unwrap_or(0);
+            let text = format!("Layer {}/{}: {}", index + 1, layers.len(), layers[index].0);
+            self.view.set_stat_message(&text);
+        }
+    }
+
+    ///pixels of the displayed image, the shown layer for layered images
+    fn source_image(&self, image_pb: &PathBuf) -> Option<&Arc<DynamicImage>> {
+        if let Some(layers) = self.model.layers.get(image_pb) {
+            let index = self.model.layer_index.get(image_pb).copied().unwrap_or(0);
+            return layers.get(index).map(|(_, images)| &images.image);
+        }
+        self.source.as_ref().filter(|(pb, _)| pb == image_pb).map(|(_, image)| image)
+    }
+
+    ///only the texture is kept, ask for the pixels once
+    fn request_source(&mut self, image_pb: PathBuf) {
+        if self.source_requested.as_ref() != Some(&image_pb) {
+            self.source_requested = Some(image_pb.clone());
+            self.model.reload_image_data(image_pb);
+        }
+    }
+
+    ///computes the histogram of the displayed image on a thread, one at a time
+    fn request_histogram(&mut self) {
+        if !self.histogram_shown {
+            return;
+        }
+        let Some(image_pb) = self.view.want_to_display.clone() else { return };
+        if self.histogram_busy {
+            self.histogram_dirty = true;
+            return;
+        }
+        let Some(image) = self.source_image(&image_pb).map(Arc::clone) else {
+            self.request_source(image_pb); //computed when the pixels arrive
+            return;
+        };
+
+        let region = if self.histogram_region {
+            Some(self.view.display.s_vc.borrow().visible_source_rect())
+        } else {
+            None
+        };
+        self.histogram_busy = true;
+        let tx = self.tx;
+        std::thread::spawn(move || {
+            let histogram = Histogram::compute(&image, region);
+            tx.send(Message::HistogramReady(histogram, image_pb));
+        });
+    }
+
+    ///asks for a destination and writes the selected part of the displayed image
+    fn export_selection(&mut self) {
+        let Some(image_pb) = self.view.want_to_display.clone() else { return };
+        let Some(crop) = self.view.display.s_vc.borrow().selection_source_rect() else {
+            self.view.set_stat_message("Select a part of the image with Shift and drag first");
+            return;
+        };
+
+        let stem = image_pb.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let Some(path) = self.view.choose_save_path("Export selection", &format!("{stem}_crop.png"), &image_pb) else { return };
+        let Some(format) = ExportFormat::from_path(&path) else { return };
+
+        let mut options = ExportOptions::new(format);
+        options.crop = Some(crop);
+        if format != ExportFormat::Png {
+            let prompt = "Quality (1-100, 100 is lossless for WebP):";
+            let Some(input) = fltk::dialog::input(screen_center().0 - 150, screen_center().1 - 50, prompt, &options.quality.to_string()) else { return };
+            match input.trim().parse::<u8>() {
+                Ok(value) if (1..=100).contains(&value) => options.quality = value,
+                _ => {
+                    self.view.set_stat_message("Quality must be a number from 1 to 100");
+                    return;
+                },
+            }
+        }
+
+        self.pending_export = Some((image_pb, path, options));
+        self.run_export();
+    }
+
+    ///saves the displayed image in another format, optionally resized and without metadata
+    fn save_as(&mut self) {
+        let Some(image_pb) = self.view.want_to_display.clone() else { return };
+        let size = match self.model.texture_cache.get(&image_pb) {
+            Some(chain) => (chain.logical_size().0 as u32, chain.logical_size().1 as u32),
+            None => return,
+        };
+        let Some(mut options) = my_save::ask_save_options(size, self.model.metadata.contains_key(&image_pb)) else { return };
+
+        let stem = image_pb.file_stem().unwrap_or_default().to_string_lossy().to_string();
+        let name = format!("{stem}.{}", options.format.extension());
+        let Some(path) = self.view.choose_save_path("Save as", &name, &image_pb) else { return };
+        if let Some(format) = ExportFormat::from_path(&path) { //the extension typed wins over the dialog
+            options.format = format;
+        }
+        self.pending_export = Some((image_pb, path, options));
+        self.run_export();
+    }
+
+    ///writes the pending export on a thread once the pixels of its image are at hand
+    fn run_export(&mut self) {
+        let Some((image_pb, _, _)) = &self.pending_export else { return };
+        let Some(image) = self.source_image(image_pb).map(Arc::clone) else { //cropped on the thread
+            let image_pb = image_pb.clone();
+            self.request_source(image_pb);
+            self.view.set_stat_message("Reading pixels...");
+            return;
+        };
+
+        let Some((image_pb, path, options)) = self.pending_export.take() else { return };
+        let metadata = self.model.metadata.get(&image_pb).cloned();
+        let tx = self.tx;
+        std::thread::spawn(move || {
+            let text = match my_export::export(&image, metadata.as_ref(), &path, &options) {
+                Ok((w, h)) => format!("Saved {w} x {h} to {}", path.display()),
+                Err(e) => format!("Saving failed: {e}"),
+            };
+            tx.send(Message::Info(text));
+        });
+    }
+
+    ///the displayed image, or the entry selected in the browser
+    fn file_op_target(&mut self) -> Option<PathBuf> {
+        let target = self.view.want_to_display.clone()
+            .or_else(|| self.view.selected_browser_item().and_then(|nr| self.model.get_item_path(nr)));
+        if target.is_none() {
+            self.view.set_stat_message("Select a file first");
+        }
+        target
+    }
+
+    ///to a folder picked in a dialog
+    fn move_or_copy_file(&mut self, copy: bool) {
+        let Some(path) = self.file_op_target() else { return };
+        let title = if copy { "Copy to" } else { "Move to" };
+        if let Some(dir) = self.view.choose_folder(title, path.parent().unwrap_or(&path)) {
+            let res = if copy { my_fileops::copy_to(&path, &dir) } else { my_fileops::move_to(&path, &dir) };
+            self.finish_file_op(res);
+        }
+    }
+
+    ///shows the selected file of the review window side by side with another file of its group
+    fn review_duplicate(&mut self) {
+        let Some(review) = self.duplicates.as_ref() else { return };
+        if !review.is_shown() {
+            if review.group_count() == 0 {
+                self.duplicates = None;
+            }
+            return;
+        }
+        let pair = review.selected().and_then(|(group, file)| {
+            let shown = review.file(group, file)?.path.clone();
+            let other = review.file(group, if file == 0 { 1 } else { 0 })?.path.clone();
+            Some((shown, other))
+        });
+        if review.group_count() == 0 {
+            self.view.set_stat_message("All duplicates are reviewed");
+        }
+
+        if let Some((shown, other)) = pair {
+            self.compare_with = Some(other);
+            if self.view.display.compare_mode() != CompareMode::SideBySide {
+                self.view.display.set_compare_mode(CompareMode::SideBySide);
+                self.view.display.set_flicker_timer(false);
+                self.view.check_menu_item("&View/C&ompare/Side &by side\t");
+            }
+            self.tx.send(Message::WantToDisplay(shown));
+        }
+    }
+
+    ///runs a model navigation and remembers the listing left for going back
+    fn navigate(&mut self, go: impl FnOnce(&mut MyModel) -> Result<(), Box<dyn Error>>) {
+        let left = self.current_visit();
+        match go(&mut self.model) {
+            Ok(()) => {
+                if self.model.get_cwd() != left.path {
+                    self.history.visit(left);
+                }
+            },
+
+            Err(e) => self.view.set_error_message(e),
+        }
+    }
+
+    fn current_visit(&self) -> Visit {
+        Visit {
+            path: self.model.get_cwd(),
+            selected: self.view.selected_browser_item().and_then(|nr| self.model.get_item_path(nr)),
+            image: self.view.want_to_display.clone(),
+        }
+    }
+
+    ///lists the previous or next visited folder or archive, selects what was selected there and shows the image that was viewed
+    fn go_history(&mut self, forward: bool) {
+        let Some(path) = self.history.peek(forward).map(|visit| visit.path.clone()) else {
+            self.view.set_stat_message(if forward { "Nothing to go forward to" } else { "Nothing to go back to" });
+            return;
+        };
+        let current = self.current_visit();
+        let listing = match self.model.listing_at(&path) {
+            Ok(listing) => listing,
+            Err(e) => {
+                self.view.set_error_message(e); //stays in the history, it may come back
+                return;
+            },
+        };
+        let Some(visit) = self.history.step(forward, current) else { return };
+
+        self.view.populate_browser(&listing, &self.model.marks);
+        self.view.set_input_text(visit.path);
+        if let Some(index) = visit.selected.and_then(|pb| self.model.get_index(&pb)) {
+            self.view.select_browser_item(index as i32);
+        }
+        match visit.image {
+            Some(image_pb) => self.tx.send(Message::WantToDisplay(image_pb)),
+            None if self.view.want_to_display.is_some() => self.tx.send(Message::StopImageDisplay),
+            None => self.view.set_browsing_layout(),
+        }
+        self.tx.send(Message::UpdateStatusData);
+    }
+
+    ///the displayed image or the image selected in the browser
+    fn mark_target(&mut self) -> Option<PathBuf> {
+        let target = self.view.want_to_display.clone()
+            .or_else(|| self.view.selected_browser_item().and_then(|nr| self.model.get_image_path(nr)));
+        if target.is_none() {
+            self.view.set_stat_message("Select an image to mark it");
+        }
+        target
+    }
+
+    ///in the statusbar when displayed, and in the browser line
+    fn show_mark(&mut self, image_pb: &PathBuf) {
+        let mark = self.model.get_mark(image_pb);
+        if self.view.want_to_display.as_ref() == Some(image_pb) {
+            self.view.set_stat_mark(&mark);
+        }
+        if let Some(index) = self.model.get_index(image_pb) {
+            self.view.set_browser_mark(index as i32 + 1, &mark);
+        }
+    }
+
+    ///moves rejected images of the current folder to the trash, or to the folder given
+    fn apply_to_rejects(&mut self, dir: Option<PathBuf>) {
+        let rejects = self.model.get_rejected();
+        if rejects.is_empty() {
+            self.view.set_stat_message("No rejected images here");
+            return;
+        }
+        let (done, errors) = match &dir {
+            Some(dir) => my_fileops::batch(&rejects, |path| my_fileops::move_to(path, dir)),
+            None => my_fileops::batch(&rejects, my_fileops::trash),
+        };
+        self.finish_batch(done, errors);
+    }
+
+    ///reports what was done and what failed, the done part is undoable
+    fn finish_batch(&mut self, done: Option<FileOp>, errors: Vec<Box<dyn Error>>) {
+        let mut text = String::new();
+        if let Some(op) = done {
+            self.move_marks(&op.moves());
+            self.after_file_change(&op.affected(), &op.moves());
+            text = op.describe();
+            self.file_ops.push(op);
+        }
+        if let Some(first) = errors.first() {
+            if !text.is_empty() {
+                text.push_str(", ");
+            }
+            text.push_str(&format!("{} failed: {first}", errors.len()));
+        }
+        self.view.set_stat_message(&text);
+    }
+
+    fn finish_file_op(&mut self, res: Result<FileOp, Box<dyn Error>>) {
+        match res {
+            Ok(op) => {
+                self.move_marks(&op.moves());
+                self.after_file_change(&op.affected(), &op.moves());
+                self.view.set_stat_message(&op.describe());
+                self.file_ops.push(op);
+            },
+
+            Err(e) => self.view.set_error_message(e),
+        }
+    }
+
+    ///marks and sidecars follow moved and renamed files
+    fn move_marks(&mut self, moves: &[(PathBuf, PathBuf)]) {
+        for (from, to) in moves {
+            if let Err(e) = self.model.move_mark(from, to) {
+                self.view.set_error_message(e);
+            }
+        }
+    }
+
+    ///drops stale listings and cached data, lists the folder again and leaves an image that is gone
+    fn after_file_change(&mut self, affected: &[PathBuf], moves: &[(PathBuf, PathBuf)]) {
+        let replacement = self.view.want_to_display.clone()
+            .filter(|shown| affected.contains(shown) && !shown.exists())
+            .map(|shown| {
+                //a renamed image is shown under its new name, otherwise a neighbour takes its place
+                let mut renamed = shown.clone();
+                for (from, to) in moves {
+                    if *from == renamed {
+                        renamed = to.clone(); //batch renames can go through a temporary name
+                    }
+                }
+                Some(renamed)
+                    .filter(|renamed| *renamed != shown && renamed.exists() && renamed.parent() == shown.parent())
+                    .or_else(|| self.model.get_next_image(shown.clone()).map(|(next, _)| next))
+                    .or_else(|| self.model.get_prev_image(shown).map(|(prev, _)| prev))
+            });
+        let selected = self.view.selected_browser_item();
+
+        for path in affected {
+            let (gpu_freed, image_freed) = self.model.invalidate(path);
+            self.gpu_data_size -= gpu_freed;
+            self.image_cache_size -= image_freed;
+            if self.source.as_ref().is_some_and(|(pb, _)| pb.starts_with(path)) {
+                self.source = None;
+            }
+        }
+
+        let cwd = self.model.get_cwd();
+        match self.model.get_listing(&cwd) {
+            Ok(listing) => {
+                self.view.populate_browser(&listing, &self.model.marks);
+                if let (Some(nr), false) = (selected, listing.is_empty()) {
+                    self.view.select_browser_item((nr - 1).min(listing.len() as i32 - 1));
+                }
+            },
+
+            Err(e) => self.view.set_error_message(e),
+        }
+
+        match replacement {
+            Some(Some(image_pb)) => self.tx.send(Message::WantToDisplay(image_pb)),
+            Some(None) => self.tx.send(Message::StopImageDisplay),
+            None => {},
+        }
+        self.tx.send(Message::UpdateStatusData);
+    }
+
+    ///puts the pixels of the displayed image, or its selected part, its path as text and a file uri list on the clipboard in one go,
+    ///images in archives keep their virtual path and get no uri since the file isn't on disk
+    fn run_copy(&mut self) {
+        let Some(image_pb) = self.pending_copy.clone() else { return };
+        let Some(image) = self.source_image(&image_pb).map(Arc::clone) else {
+            self.request_source(image_pb);
+            self.view.set_stat_message("Reading pixels...");
+            return;
+        };
+        self.pending_copy = None;
+
+        let selection = self.view.display.s_vc.borrow().selection_source_rect();
+        let tx = self.tx;
+        std::thread::spawn(move || { //cropped and converted here, the clipboard is set when it comes back
+            let pixels = match selection {
+                Some(rect) => {
+                    let (x, y, w, h) = my_export::pixel_rect(rect, (image.width() as f32, image.height() as f32));
+                    image.crop_imm(x, y, w, h).to_rgba8()
+                },
+                None => image.to_rgba8(),
+            };
+            tx.send(Message::CopyReady(DynamicImage::ImageRgba8(pixels), image_pb));
+        });
+    }
+
+    fn copy_to_clipboard(&mut self, pixels: DynamicImage, image_pb: &Path) -> Result<(), Box<dyn Error>> {
+        let mut contents = vec![
+            ClipboardContent::Image(RustImageData::from_dynamic_image(pixels)),
+            ClipboardContent::Text(image_pb.display().to_string()),
+        ];
+        if image_pb.is_file() {
+            contents.push(ClipboardContent::Other(String::from("text/uri-list"), format!("{}\r\n", file_uri(image_pb)).into_bytes()));
+        }
+
+        if self.clipboard.is_none() {
+            self.clipboard = Some(ClipboardContext::new()?);
+        }
+        let clipboard = self.clipboard.as_ref().expect("Clipboard was created above.");
+        clipboard.set(contents)?;
+        Ok(())
+    }
+
+    ///shows coordinates and color of the pixel under the mouse in the statusbar
+    fn inspect_pixel(&mut self, point: Option<(f32, f32)>) {
+        self.pixel_hex = None;
+        let (Some(image_pb), Some((u, v))) = (self.view.want_to_display.clone(), point) else {
+            self.view.set_stat_pixel("");
+            return;
+        };
+
+        let Some(image) = self.source_image(&image_pb) else {
+            self.request_source(image_pb);
+            self.view.set_stat_pixel("Reading pixels...");
+            return;
+        };
+
+        let (w, h) = image.dimensions();
+        let (x, y) = (((u * w as f32) as u32).min(w - 1), ((v * h as f32) as u32).min(h - 1));
+        let [r, g, b, a] = image.get_pixel(x, y).0;
+        let floats = match image {
+            DynamicImage::ImageRgb32F(buf) => {
+                let [fr, fg, fb] = buf.get_pixel(x, y).0;
+                Some(format!("  ({fr:.3} {fg:.3} {fb:.3})"))
+            },
+            DynamicImage::ImageRgba32F(buf) => {
+                let [fr, fg, fb, fa] = buf.get_pixel(x, y).0;
+                Some(format!("  ({fr:.3} {fg:.3} {fb:.3} {fa:.3})"))
+            },
+            _ => None,
+        };
+
+        //coordinates follow the zoomable size, which differs from the raster for vector images
+        let (l_w, l_h) = self.model.texture_cache.get(&image_pb).map(|chain| chain.logical_size()).unwrap_or((w as f32, h as f32));
+        let text = format!("{}, {}  RGBA {r} {g} {b} {a}{}", (u * l_w) as u32, (v * l_h) as u32, floats.unwrap_or_default());
+        self.pixel_hex = Some(format!("#{r:02X}{g:02X}{b:02X}{a:02X}"));
+        self.view.set_stat_pixel(&text);
+    }
+
+    ///only known for images on the gpu, unknown images are treated as pages
+    fn is_wide(&self, image_pb: &PathBuf) -> bool {
+        if let Some(handle) = self.model.texture_cache.get(image_pb) {
+            let size = handle.base().size();
+            return size.x > size.y;
+        }
+        false
+    }
+
+    fn get_slideshow_next(&self) -> Option<PathBuf> {
+        let current = self.view.want_to_display.clone()?;
+        if self.slideshow.shuffle {
+            return self.slideshow.next_shuffled(&current);
+        }
+        if let Some((next, _)) = self.model.get_next_image(current) {
+            Some(next)
+        } else if self.slideshow.looping {
+            self.model.get_image_list().first().cloned()
+        } else {
+            None
+        }
+    }
+
+    ///in two page mode the image after the displayed one goes on the second page, when comparing the picked one
+    fn update_partner(&mut self) {
+        self.view.partner = None;
+        self.view.display.set_second_image(None);
+        self.clear_difference();
+
+        let Some(current) = self.view.want_to_display.clone() else { return };
+        let partner = if self.view.display.compare_mode() != CompareMode::Off {
+            self.compare_with.clone()
+                .filter(|pb| *pb != current)
+                .or_else(|| self.model.get_next_image(current).map(|(next, _)| next))
+        } else if self.view.display.spread_mode() != SpreadMode::Single {
+            self.model.get_next_image(current).map(|(next, _)| next)
+        } else {
+            None
+        };
+
+        if self.partner_source.as_ref().map(|(pb, _)| pb) != partner.as_ref() {
+            self.partner_source = None;
+            self.partner_requested = None;
+        }
+        if let Some(partner) = partner {
+            self.view.partner = Some(partner.clone());
+            if let Some(handle) = self.get_texture(&partner) {
+                self.view.display.set_second_image(Some(handle));
+            } else {
+                self.model.load_image_data(partner);
+            }
+        }
+        self.request_compare();
+    }
+
+    fn clear_difference(&mut self) {
+        self.gpu_data_size -= self.difference_size;
+        self.difference_size = 0;
+        self.compared = None;
+        self.view.display.set_difference(None);
+    }
+
+    ///computes the difference image and psnr/ssim for the displayed image and its partner on a thread
+    fn request_compare(&mut self) {
+        if self.view.display.compare_mode() == CompareMode::Off {
+            return;
+        }
+        let (Some(a_pb), Some(b_pb)) = (self.view.want_to_display.clone(), self.view.partner.clone()) else { return };
+        if self.compared == Some((a_pb.clone(), b_pb.clone())) {
+            return; //done or underway
+        }
+
+        //shared, not copied, the thread only reads them
+        let Some(a) = self.source_image(&a_pb).map(Arc::clone) else {
+            self.request_source(a_pb);
+            return;
+        };
+        let Some(b) = self.partner_source.as_ref().filter(|(pb, _)| *pb == b_pb).map(|(_, image)| Arc::clone(image)) else {
+            if self.partner_requested.as_ref() != Some(&b_pb) {
+                self.partner_requested = Some(b_pb.clone());
+                self.model.reload_image_data(b_pb);
+            }
+            return;
+        };
+
+        self.compared = Some((a_pb.clone(), b_pb.clone()));
+        let tx = self.tx;
+        std::thread::spawn(move || {
+            let (difference, stats) = my_compare::compare(&a, &b);
+            tx.send(Message::CompareReady(MipImages::build(difference), stats, a_pb, b_pb));
+        });
+    }
 }
 
+///percent encodes everything but unreserved characters and the separators
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_encoded_bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-._~".contains(&byte) {
+            uri.push(byte as char);
+        } else {
+            uri.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    uri
+}
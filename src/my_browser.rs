@@ -1,31 +1,46 @@
-use fltk::{app, browser::HoldBrowser, enums::{Event, Key}, prelude::{BrowserExt, WidgetBase}};
+use fltk::{app, browser::MultiBrowser, enums::{Event, Key}, prelude::{BrowserExt, WidgetBase}};
 
 use fltk::app::event_button;
 use fltk::app::event_clicks;
 use fltk::app::event_is_click;
 use fltk::app::event_key;
 
-use crate::{EntryType, Listing, Message};
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::{my_marks::Mark, EntryType, Listing, Message, MOUSE_BACK, MOUSE_FORWARD};
 
 pub struct MyBrowser {
-    pub browser: HoldBrowser,
+    pub browser: MultiBrowser, //ctrl and shift select several entries, for batch rename
 }
 
 impl MyBrowser {
     pub fn new(tx: app::Sender<Message>) -> Self {
-        let mut browser = HoldBrowser::default_fill();
-        browser.set_column_widths(&[270,20]);
+        let mut browser = MultiBrowser::default_fill();
+        browser.set_column_widths(&[270,100]); //name, mark and size
 
         browser.handle(move |widget, event| {
             match event {
                 Event::Push => {
+                    match event_button() {
+                        MOUSE_BACK => {
+                            tx.send(Message::GoBack);
+                            return true;
+                        },
+
+                        MOUSE_FORWARD => {
+                            tx.send(Message::GoForward);
+                            return true;
+                        },
+
+                        _ => {},
+                    }
                     if event_is_click() {
-                        if !widget.selected_items().is_empty() {
+                        if widget.value() > 0 { //the last selected line
                             if event_clicks() && event_button() == 1 { //double or more clicks
-                                tx.send(Message::OpenItem(widget.selected_items()[0])); // all this could be moved to a controller?
+                                tx.send(Message::OpenItem(widget.value())); // all this could be moved to a controller?
                                 return true;
                             } else if event_button() == 3 {
-                                tx.send(Message::UpDir(widget.selected_items()[0]));
+                                tx.send(Message::UpDir(widget.value()));
                                 return true;
                             }
                         } else if event_button() == 3 {
@@ -39,16 +54,16 @@ impl MyBrowser {
                 Event::KeyDown => {
                     match event_key() {
                         Key::Enter => {
-                            if !widget.selected_items().is_empty() {
-                                tx.send(Message::OpenItem(widget.selected_items()[0]));
+                            if widget.value() > 0 {
+                                tx.send(Message::OpenItem(widget.value()));
                                 return true;
                             }
                             false
                         },
 
                         Key::BackSpace => {
-                            if !widget.selected_items().is_empty() {
-                                tx.send(Message::UpDir(widget.selected_items()[0]));
+                            if widget.value() > 0 {
+                                tx.send(Message::UpDir(widget.value()));
                             } else {
                                 tx.send(Message::UpDir(1)); //1 is the default
                             }
@@ -82,7 +97,7 @@ impl MyBrowser {
         }
     }
 
-    pub fn populate_browser(&mut self, listing: &Vec<Listing>) {
+    pub fn populate_browser(&mut self, listing: &Vec<Listing>, marks: &HashMap<PathBuf, Mark>) {
         use human_bytes::human_bytes;
         self.browser.clear();
 
@@ -96,8 +111,21 @@ impl MyBrowser {
                     EntryType::Image => "",
                 };
                 let size = human_bytes(l.size as u32);
-                self.browser.add(&format!("{}{}\t\t{}", type_prefix, &l.display_name, size));
+                let mark = marks.get(&l.file_path).map(|mark| mark.label()).unwrap_or_default();
+                self.browser.add(&format!("{}{}\t{}\t{}", type_prefix, &l.display_name, mark, size));
+            }
+        }
+    }
+
+    ///replaces the mark column of a line, 1 based like the browser
+    pub fn set_mark(&mut self, line: i32, mark: &Mark) {
+        if let Some(text) = self.browser.text(line) {
+            let label = mark.label();
+            let mut columns: Vec<&str> = text.splitn(3, '\t').collect();
+            if columns.len() == 3 {
+                columns[1] = &label;
+                self.browser.set_text(line, &columns.join("\t"));
             }
         }
     }
-}
\ No newline at end of file
+}
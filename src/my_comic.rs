@@ -0,0 +1,135 @@
+pub const COMIC_INFO_NAME: &str = "ComicInfo.xml";
+
+///the parts of ComicInfo.xml that are shown, see the anansi project for the full schema
+#[derive(Clone, Default)]
+pub struct ComicInfo {
+    pub series: Option<String>,
+    pub number: Option<String>,
+    pub volume: Option<String>,
+    pub title: Option<String>,
+    pub writer: Option<String>,
+    pub penciller: Option<String>,
+    pub publisher: Option<String>,
+    pub year: Option<String>,
+    pub summary: Option<String>,
+    pub pages: Vec<usize>, //image indexes in reading order
+}
+
+impl ComicInfo {
+    ///a forgiving reader, ComicInfo.xml is flat enough to not need a real xml parser
+    pub fn parse(xml: &str) -> Self {
+        Self {
+            series: element_text(xml, "Series"),
+            number: element_text(xml, "Number"),
+            volume: element_text(xml, "Volume"),
+            title: element_text(xml, "Title"),
+            writer: element_text(xml, "Writer"),
+            penciller: element_text(xml, "Penciller"),
+            publisher: element_text(xml, "Publisher"),
+            year: element_text(xml, "Year"),
+            summary: element_text(xml, "Summary"),
+            pages: page_images(xml),
+        }
+    }
+
+    ///series, issue and title for the window label
+    pub fn label(&self) -> Option<String> {
+        let mut parts = Vec::new();
+        if let Some(series) = &self.series {
+            match &self.number {
+                Some(number) => parts.push(format!("{series} #{number}")),
+                None => parts.push(series.clone()),
+            }
+        }
+        if let Some(title) = &self.title {
+            parts.push(title.clone());
+        }
+        if parts.is_empty() {
+            None
+        } else {
+            Some(parts.join(" - "))
+        }
+    }
+
+    ///all known fields, one per line
+    pub fn details(&self) -> String {
+        let fields = [
+            ("Series", &self.series),
+            ("Number", &self.number),
+            ("Volume", &self.volume),
+            ("Title", &self.title),
+            ("Writer", &self.writer),
+            ("Penciller", &self.penciller),
+            ("Publisher", &self.publisher),
+            ("Year", &self.year),
+            ("Summary", &self.summary),
+        ];
+
+        let mut res = String::new();
+        for (name, value) in fields {
+            if let Some(value) = value {
+                res += &format!("{name}:\t{value}\n");
+            }
+        }
+        if !self.pages.is_empty() {
+            res += &format!("Pages:\t{}\n", self.pages.len());
+        }
+        res
+    }
+}
+
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    let text = unescape(xml[start..end].trim());
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+///the Image attribute of every <Page> in the <Pages> list
+fn page_images(xml: &str) -> Vec<usize> {
+    let mut pages = Vec::new();
+    let mut rest = xml;
+    while let Some(start) = rest.find("<Page ") {
+        rest = &rest[start + 6..];
+        let tag_end = rest.find('>').unwrap_or(rest.len());
+        if let Some(image) = attribute(&rest[..tag_end], "Image") {
+            if let Ok(index) = image.trim().parse::<usize>() {
+                pages.push(index);
+            }
+        }
+        rest = &rest[tag_end..];
+    }
+    pages
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        let pattern = format!("{name}={quote}");
+        let mut search_from = 0;
+        while let Some(found) = tag[search_from..].find(&pattern) {
+            let pos = search_from + found;
+            //don't match the end of a longer attribute name
+            if pos == 0 || tag[..pos].ends_with(char::is_whitespace) {
+                let start = pos + pattern.len();
+                let end = tag[start..].find(quote)? + start;
+                return Some(&tag[start..end]);
+            }
+            search_from = pos + pattern.len();
+        }
+    }
+    None
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,107 @@
+const DIFFERENCE_GAIN: f32 = 8.; //small differences are invisible without amplifying
+const SSIM_WINDOW: u32 = 8;
+const SSIM_C1: f64 = (0.01 * 255.) * (0.01 * 255.);
+const SSIM_C2: f64 = (0.03 * 255.) * (0.03 * 255.);
+
+use image::{imageops::FilterType, DynamicImage, GrayImage, RgbImage};
+
+///how similar two images are, the second is scaled to the first when sizes differ
+#[derive(Clone, Copy)]
+pub struct CompareStats {
+    pub psnr: f64, //db, infinite for identical images
+    pub ssim: Option<f64>, //none for an empty image
+    pub scaled: bool,
+}
+
+impl CompareStats {
+    pub fn summary(&self) -> String {
+        let psnr = if self.psnr.is_finite() { format!("{:.2} dB", self.psnr) } else { String::from("identical") };
+        let scaled = if self.scaled { ", second image scaled to the first" } else { "" };
+        let ssim = self.ssim.map_or(String::from("unavailable"), |ssim| format!("{ssim:.4}"));
+        format!("PSNR {psnr}  SSIM {ssim}{scaled}")
+    }
+}
+
+///the amplified absolute difference and the similarity of the two images
+pub fn compare(a: &DynamicImage, b: &DynamicImage) -> (DynamicImage, CompareStats) {
+    let a = a.to_rgb8();
+    let scaled = a.dimensions() != (b.width(), b.height());
+    let b = if scaled {
+        b.resize_exact(a.width(), a.height(), FilterType::Triangle).to_rgb8()
+    } else {
+        b.to_rgb8()
+    };
+
+    let difference = difference(&a, &b);
+    let stats = CompareStats {
+        psnr: psnr(&a, &b),
+        ssim: ssim(&DynamicImage::ImageRgb8(a).to_luma8(), &DynamicImage::ImageRgb8(b).to_luma8()),
+        scaled,
+    };
+    (DynamicImage::ImageRgb8(difference), stats)
+}
+
+fn difference(a: &RgbImage, b: &RgbImage) -> RgbImage {
+    let mut res = RgbImage::new(a.width(), a.height());
+    for ((out, pa), pb) in res.pixels_mut().zip(a.pixels()).zip(b.pixels()) {
+        for c in 0..3 {
+            let diff = (pa.0[c] as f32 - pb.0[c] as f32).abs() * DIFFERENCE_GAIN;
+            out.0[c] = diff.min(255.) as u8;
+        }
+    }
+    res
+}
+
+///over all rgb samples
+fn psnr(a: &RgbImage, b: &RgbImage) -> f64 {
+    let sum: f64 = a.as_raw().iter().zip(b.as_raw().iter())
+        .map(|(&x, &y)| {
+            let d = x as f64 - y as f64;
+            d * d
+        })
+        .sum();
+    let mse = sum / a.as_raw().len().max(1) as f64;
+    if mse == 0. {
+        f64::INFINITY
+    } else {
+        10. * (255. * 255. / mse).log10()
+    }
+}
+
+///mean of the structural similarity over non overlapping windows of the luminance, an image smaller than a window is one window
+fn ssim(a: &GrayImage, b: &GrayImage) -> Option<f64> {
+    let (w, h) = a.dimensions();
+    let (win_w, win_h) = (SSIM_WINDOW.min(w), SSIM_WINDOW.min(h));
+    if win_w == 0 || win_h == 0 {
+        return None;
+    }
+    let mut total = 0.;
+    let mut windows = 0;
+
+    for wy in (0..=h - win_h).step_by(win_h as usize) {
+        for wx in (0..=w - win_w).step_by(win_w as usize) {
+            let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab) = (0., 0., 0., 0., 0.);
+            for y in wy..wy + win_h {
+                for x in wx..wx + win_w {
+                    let va = a.get_pixel(x, y).0[0] as f64;
+                    let vb = b.get_pixel(x, y).0[0] as f64;
+                    sum_a += va;
+                    sum_b += vb;
+                    sum_aa += va * va;
+                    sum_bb += vb * vb;
+                    sum_ab += va * vb;
+                }
+            }
+            let n = (win_w * win_h) as f64;
+            let (mean_a, mean_b) = (sum_a / n, sum_b / n);
+            let var_a = sum_aa / n - mean_a * mean_a;
+            let var_b = sum_bb / n - mean_b * mean_b;
+            let cov = sum_ab / n - mean_a * mean_b;
+
+            total += ((2. * mean_a * mean_b + SSIM_C1) * (2. * cov + SSIM_C2))
+                / ((mean_a * mean_a + mean_b * mean_b + SSIM_C1) * (var_a + var_b + SSIM_C2));
+            windows += 1;
+        }
+    }
+    Some(total / windows as f64)
+}
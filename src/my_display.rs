@@ -1,18 +1,202 @@
-use std::{cell::RefCell, error::Error, rc::Rc};
+use std::{borrow::Cow, cell::RefCell, error::Error, rc::Rc, sync::Arc, time::Instant};
 
-use fltk::{app::{self, event_button, event_dx_value, event_dy_value, event_key, event_key_down, event_state}, enums::{Event, Key}, prelude::{WidgetBase, WidgetExt}, window::{self, GlutWindow}};
-use image::{DynamicImage, EncodableLayout};
-use speedy2d::{dimen::Vector2, image::ImageHandle, shape::Rectangle};
+use fltk::{app::{self, TimeoutHandle, event_button, event_dx_value, event_dy_value, event_key, event_key_down, event_state}, enums::{Event, Key}, prelude::{WidgetBase, WidgetExt}, window::{self, GlutWindow}};
+use image::{imageops::FilterType, DynamicImage};
+use speedy2d::{color::Color, dimen::Vector2, image::ImageHandle, shape::Rectangle, Graphics2D};
 
-use crate::Message;
+use crate::{my_histogram::{Histogram, BINS}, CompareMode, Message, Orientation, SpreadMode, ZoomModifier, MOUSE_BACK, MOUSE_FORWARD};
 use crate::ViewSettings;
 
+const MIP_MIN_SIZE: u32 = 64; //don't make mip levels smaller than this
+const CROSSFADE_SECONDS: f32 = 0.6;
+const MIN_FRAME_SECONDS: f64 = 0.02; //frames without a delay are shown this long
+const MAX_VECTOR_TEXTURE_SIZE: f32 = 8192.; //longest side when rasterizing vector images
+const WHEEL_ZOOM_STEP: f32 = 1.2; //pr wheel notch
+const SMOOTH_ZOOM_RATE: f32 = 0.25; //part of the remaining zoom applied pr idle step
+const SMOOTH_ZOOM_DONE: f32 = 0.002; //log of a zoom factor too small to animate
+const FLICKER_SECONDS: f64 = 0.5;
+const WIPE_GRAB_DISTANCE: f32 = 8.; //pixels from the wipe line that start dragging it
+const HISTOGRAM_HEIGHT: f32 = 100.;
+const HISTOGRAM_MARGIN: f32 = 10.;
+const MINIMAP_SIZE: f32 = 160.; //longest side
+const MINIMAP_MARGIN: f32 = 10.;
+
 pub struct MyDisplay {
     pub glut_win: window::GlutWindow,
-    pub(crate)s_displaying_image: Rc<RefCell<Option<ImageHandle>>>,
+    pub(crate)s_displaying_image: Rc<RefCell<Option<MipChain>>>,
+    s_second_image: Rc<RefCell<Option<MipChain>>>,
+    s_spread: Rc<RefCell<SpreadMode>>,
+    s_compare: Rc<RefCell<CompareMode>>,
+    s_difference: Rc<RefCell<Option<MipChain>>>,
+    s_wipe: Rc<RefCell<f32>>, //position of the wipe line as a fraction of the window width
+    s_flicker_second: Rc<RefCell<bool>>, //flicker is showing the second image
+    s_flicker_timeout: Rc<RefCell<Option<TimeoutHandle>>>,
+    s_zoom_modifier: Rc<RefCell<ZoomModifier>>,
+    s_crossfade: Rc<RefCell<Option<Crossfade>>>,
+    s_animation: Rc<RefCell<Option<Animation>>>,
+    s_animation_timeout: Rc<RefCell<Option<TimeoutHandle>>>,
+    s_histogram: Rc<RefCell<Option<Histogram>>>,
+    s_minimap: Rc<RefCell<bool>>,
     pub s_vc: Rc<RefCell<ViewConfig>>,
 }
 
+///uploaded frames of an animated image
+#[derive(Clone)]
+pub struct Animation {
+    frames: Vec<MipChain>,
+    delays: Vec<f64>,
+    current: usize,
+}
+
+impl Animation {
+    pub fn new(frames: Vec<MipChain>, delays: Vec<f64>) -> Self {
+        Self { frames, delays, current: 0 }
+    }
+
+    pub fn first_frame(&self) -> &MipChain {
+        &self.frames[0]
+    }
+
+    ///bytes uploaded for all frames
+    pub fn data_size(&self) -> usize {
+        self.frames.iter().map(|frame| frame.data_size()).sum()
+    }
+
+    fn frame(&self) -> &MipChain {
+        &self.frames[self.current]
+    }
+
+    fn delay(&self) -> f64 {
+        self.delays[self.current].max(MIN_FRAME_SECONDS)
+    }
+
+    ///goes to the next frame and returns how long to show it
+    fn advance(&mut self) -> f64 {
+        self.current = (self.current + 1) % self.frames.len();
+        self.delay()
+    }
+}
+
+///the previous image as it was drawn, fading out below the new one
+struct Crossfade {
+    chain: MipChain,
+    position: Rectangle,
+    coords: Rectangle,
+    orientation: Orientation,
+    scale: f32,
+    start: Instant,
+}
+
+impl Crossfade {
+    ///alpha for the new image
+    fn progress(&self) -> f32 {
+        (self.start.elapsed().as_secs_f32() / CROSSFADE_SECONDS).min(1.)
+    }
+}
+
+///where the two pages of a spread meet, in normalized coordinates of both pages together
+#[derive(Clone, Copy)]
+struct SpreadGeometry {
+    left_is_primary: bool,
+    split: f32,
+    second_scale: f32, //the second page is scaled to the height of the first
+}
+
+///a decoded image and its successively halved copies, made on the decoding thread so uploading only copies to the gpu
+#[derive(Clone)]
+pub struct MipImages {
+    pub image: Arc<DynamicImage>, //the source pixels, kept as decoded and shared with the worker threads
+    converted: Option<DynamicImage>, //8 bit copy of the full size image when it isn't 8 bit rgb or rgba already
+    smaller: Vec<DynamicImage>, //8 bit rgb or rgba
+}
+
+impl MipImages {
+    ///converts and resizes, so not for the ui thread
+    pub fn build(image: DynamicImage) -> Self {
+        let converted = match as_8_bit(&image) {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(converted) => Some(converted),
+        };
+        let mut smaller: Vec<DynamicImage> = Vec::new();
+        loop {
+            let level = smaller.last().or(converted.as_ref()).unwrap_or(&image);
+            let (w, h) = (level.width() / 2, level.height() / 2);
+            if w < MIP_MIN_SIZE || h < MIP_MIN_SIZE {
+                break;
+            }
+            //triangle is a tent filter over two source pixels, enough when halving
+            let half = level.resize_exact(w, h, FilterType::Triangle);
+            smaller.push(half);
+        }
+        MipImages { image: Arc::new(image), converted, smaller }
+    }
+
+    ///the full size level as the gpu takes it
+    fn base(&self) -> &DynamicImage {
+        self.converted.as_ref().unwrap_or(&*self.image)
+    }
+
+    ///bytes of the image and its copies
+    pub fn data_size(&self) -> usize {
+        let levels = self.converted.iter().chain(&self.smaller);
+        self.image.as_bytes().len() + levels.map(|level| level.as_bytes().len()).sum::<usize>()
+    }
+}
+
+///borrowed when it already is what the gpu takes
+fn as_8_bit(image: &DynamicImage) -> Cow<'_, DynamicImage> {
+    match image {
+        DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => Cow::Borrowed(image),
+        _ if image.color().has_alpha() => Cow::Owned(DynamicImage::ImageRgba8(image.to_rgba8())),
+        _ => Cow::Owned(DynamicImage::ImageRgb8(image.to_rgb8())),
+    }
+}
+
+///an uploaded image and its prefiltered, successively halved copies
+#[derive(Clone)]
+pub struct MipChain {
+    levels: Vec<ImageHandle>,
+    data_size: usize,
+    logical_size: (f32, f32), //size used for zooming, differs from the texture for vector images
+    raster_scale: f32, //texels per logical pixel
+    vector: bool,
+}
+
+impl MipChain {
+    ///marks the texture as a rasterized vector image of the given size
+    pub fn with_vector_size(mut self, logical_size: (f32, f32), raster_scale: f32) -> Self {
+        self.logical_size = logical_size;
+        self.raster_scale = raster_scale;
+        self.vector = true;
+        self
+    }
+
+    pub fn logical_size(&self) -> (f32, f32) {
+        self.logical_size
+    }
+
+    ///the full size texture
+    pub fn base(&self) -> &ImageHandle {
+        &self.levels[0]
+    }
+
+    ///bytes uploaded for all levels
+    pub fn data_size(&self) -> usize {
+        self.data_size
+    }
+
+    ///picks the smallest level that still has at least one texel per screen pixel
+    pub fn level_for_scale(&self, scale: f32) -> &ImageHandle {
+        let mut level = 0;
+        let mut level_scale = scale / self.raster_scale;
+        while level_scale <= 0.5 && level + 1 < self.levels.len() {
+            level_scale *= 2.;
+            level += 1;
+        }
+        &self.levels[level]
+    }
+}
+
 impl MyDisplay {
     pub fn build(mut glut_win: GlutWindow, tx: app::Sender<Message>) -> Self {
         glut_win.show();
@@ -27,11 +211,26 @@ impl MyDisplay {
         println!("renderer ok");
 
         let s_renderer: Rc<RefCell<speedy2d::GLRenderer>> = Rc::from(RefCell::from(renderer));
-        let s_displaying_image: Rc<RefCell<Option<ImageHandle>>> = Rc::from(RefCell::from(None));
+        let s_displaying_image: Rc<RefCell<Option<MipChain>>> = Rc::from(RefCell::from(None));
+        let s_texture_scale = Rc::new(RefCell::new(1.));
+        let s_second_image: Rc<RefCell<Option<MipChain>>> = Rc::from(RefCell::from(None));
+        let s_spread = Rc::new(RefCell::new(SpreadMode::Single));
+        let s_spread_geometry: Rc<RefCell<Option<SpreadGeometry>>> = Rc::new(RefCell::new(None));
+        let s_compare = Rc::new(RefCell::new(CompareMode::Off));
+        let s_difference: Rc<RefCell<Option<MipChain>>> = Rc::new(RefCell::new(None));
+        let s_wipe = Rc::new(RefCell::new(0.5));
+        let s_flicker_second = Rc::new(RefCell::new(false));
+        let s_zoom_modifier = Rc::new(RefCell::new(ZoomModifier::Ctrl));
+        let s_crossfade: Rc<RefCell<Option<Crossfade>>> = Rc::new(RefCell::new(None));
+        let s_animation: Rc<RefCell<Option<Animation>>> = Rc::new(RefCell::new(None));
+        let s_histogram: Rc<RefCell<Option<Histogram>>> = Rc::new(RefCell::new(None));
+        let s_minimap = Rc::new(RefCell::new(false));
         let s_position_in_vp = Rc::new(RefCell::new(Rectangle::new(Vector2::new(0., 0.), Vector2::new(200., 200.))));
         let s_image_coords = Rc::new(RefCell::new(Rectangle::new(Vector2::new(0., 0.), Vector2::new(1., 1.))));
         let s_centerpos_x = Rc::new(RefCell::new(0.5));
         let s_centerpos_y = Rc::new(RefCell::new(0.5));
+        let s_orientation = Rc::new(RefCell::new(Orientation::default()));
+        let s_selection: Rc<RefCell<Option<(f32, f32, f32, f32)>>> = Rc::new(RefCell::new(None));
 
         //using these variables in callbacks and methods
         let vc = ViewConfig {
@@ -40,6 +239,12 @@ impl MyDisplay {
             s_position_in_vp: s_position_in_vp.clone(),
             s_image_coords: s_image_coords.clone(),
             s_displaying_image: s_displaying_image.clone(),
+            s_texture_scale: s_texture_scale.clone(),
+            s_second_image: s_second_image.clone(),
+            s_spread: s_spread.clone(),
+            s_spread_geometry: s_spread_geometry.clone(),
+            s_compare: s_compare.clone(),
+            s_selection: s_selection.clone(),
             zoom_lvl_x_effective: 1.,
             zoom_lvl_y_effective: 1.,
             onepix_modifier_x: 1.,
@@ -55,6 +260,9 @@ impl MyDisplay {
             zoom_lvl_y: 1.,
             s_centerpos_x: s_centerpos_x.clone(),
             s_centerpos_y: s_centerpos_y.clone(),
+            s_orientation: s_orientation.clone(),
+            pending_zoom: 0.,
+            zoom_anchor: (0, 0),
             
             tx: tx,
         };
@@ -66,18 +274,139 @@ impl MyDisplay {
             let displaying_image = s_displaying_image.clone();
             let position_in_vp = s_position_in_vp.clone();
             let image_coords = s_image_coords.clone();
+            let texture_scale = s_texture_scale.clone();
+            let orientation = s_orientation.clone();
+            let second_image = s_second_image.clone();
+            let spread_geometry = s_spread_geometry.clone();
+            let compare = s_compare.clone();
+            let difference = s_difference.clone();
+            let wipe = s_wipe.clone();
+            let flicker_second = s_flicker_second.clone();
+            let crossfade = s_crossfade.clone();
+            let animation = s_animation.clone();
+            let histogram = s_histogram.clone();
+            let minimap = s_minimap.clone();
+            let selection = s_selection.clone();
             
             move |widget| {
-                if let Some(handle) = displaying_image.borrow().as_ref() {
+                if let Some(chain) = displaying_image.borrow().as_ref() {
+                    let animation = animation.borrow();
+                    let chain = match animation.as_ref() {
+                        Some(animation) => animation.frame(),
+                        None => chain,
+                    };
+                    let scale = *texture_scale.borrow();
                     ren.borrow_mut().set_viewport_size_pixels(Vector2::new(widget.pixel_w() as u32, widget.pixel_h() as u32));
                     ren.borrow_mut().draw_frame(|graphics| {
-                        graphics.clear_screen(speedy2d::color::Color::DARK_GRAY);
+                        graphics.clear_screen(Color::DARK_GRAY);
+
+                        let mut tint = Color::WHITE;
+                        if let Some(fade) = crossfade.borrow().as_ref() {
+                            draw_oriented(graphics, &fade.position, &fade.coords, fade.orientation, fade.chain.level_for_scale(fade.scale), Color::WHITE);
+                            tint = Color::from_rgba(1., 1., 1., fade.progress());
+                        }
                         
-                        graphics.draw_rectangle_image_subset_tinted(
-                            position_in_vp.borrow().as_ref(),
-                            speedy2d::color::Color::WHITE,
-                            image_coords.borrow().as_ref(),
-                            handle);
+                        match (*compare.borrow(), spread_geometry.borrow().as_ref(), second_image.borrow().as_ref(), difference.borrow().as_ref()) {
+                            (CompareMode::SideBySide, _, Some(second), _) => {
+                                let transposed = orientation.borrow().is_transposed();
+                                let oriented_size = |(w, h): (f32, f32)| if transposed { (h, w) } else { (w, h) };
+                                draw_side_by_side(
+                                    graphics,
+                                    &position_in_vp.borrow(),
+                                    &image_coords.borrow(),
+                                    *orientation.borrow(),
+                                    chain.level_for_scale(scale),
+                                    second.level_for_scale(scale), //same zoom, so the same screen pixels per image pixel
+                                    [oriented_size(chain.logical_size()), oriented_size(second.logical_size())],
+                                    widget.width() as f32 / 2.,
+                                    widget.height() as f32,
+                                    tint);
+                            },
+
+                            (CompareMode::Difference, _, _, Some(difference)) => {
+                                draw_oriented(
+                                    graphics,
+                                    &position_in_vp.borrow(),
+                                    &image_coords.borrow(),
+                                    *orientation.borrow(),
+                                    difference.level_for_scale(scale),
+                                    tint);
+                            },
+
+                            (CompareMode::Wipe, _, Some(second), _) => {
+                                let second_scale = chain.logical_size().0 / second.logical_size().0;
+                                let split = *wipe.borrow() * widget.width() as f32;
+                                let position = position_in_vp.borrow();
+                                let coords = image_coords.borrow();
+                                let halves = [
+                                    (chain.level_for_scale(scale), f32::MIN, split),
+                                    (second.level_for_scale(scale * second_scale), split, f32::MAX),
+                                ];
+                                for (handle, x0, x1) in halves {
+                                    draw_clipped(graphics, &position, &coords, *orientation.borrow(), handle, x0, x1, tint);
+                                }
+                                graphics.draw_line(Vector2::new(split, 0.), Vector2::new(split, widget.height() as f32), 2., Color::WHITE);
+                            },
+
+                            (CompareMode::Flicker, _, Some(second), _) if *flicker_second.borrow() => {
+                                let second_scale = chain.logical_size().0 / second.logical_size().0;
+                                draw_oriented(
+                                    graphics,
+                                    &position_in_vp.borrow(),
+                                    &image_coords.borrow(),
+                                    *orientation.borrow(),
+                                    second.level_for_scale(scale * second_scale),
+                                    tint);
+                            },
+
+                            (CompareMode::Off, Some(geometry), Some(second), _) => {
+                                draw_spread(
+                                    graphics,
+                                    &position_in_vp.borrow(),
+                                    &image_coords.borrow(),
+                                    *orientation.borrow(),
+                                    geometry,
+                                    chain.level_for_scale(scale),
+                                    second.level_for_scale(scale * geometry.second_scale),
+                                    tint);
+                            },
+
+                            _ => {
+                                draw_oriented(
+                                    graphics,
+                                    &position_in_vp.borrow(),
+                                    &image_coords.borrow(),
+                                    *orientation.borrow(),
+                                    chain.level_for_scale(scale),
+                                    tint);
+                            },
+                        }
+
+                        if let Some(selection) = *selection.borrow() {
+                            draw_selection(graphics, &position_in_vp.borrow(), &image_coords.borrow(), selection);
+                        }
+
+                        if let Some(histogram) = histogram.borrow().as_ref() {
+                            draw_histogram(graphics, histogram, widget.height() as f32);
+                        }
+
+                        if *minimap.borrow() {
+                            let (w, h) = chain.logical_size();
+                            let minimap_scale = MINIMAP_SIZE / w.max(h);
+                            let geometry = spread_geometry.borrow();
+                            let second = second_image.borrow();
+                            let spread = match (*compare.borrow(), geometry.as_ref(), second.as_ref()) {
+                                (CompareMode::Off, Some(geometry), Some(second)) => Some((geometry, second.level_for_scale(minimap_scale * geometry.second_scale))),
+                                _ => None,
+                            };
+                            draw_minimap(
+                                graphics,
+                                &minimap_rect(widget.width() as f32, &position_in_vp.borrow(), &image_coords.borrow()),
+                                &image_coords.borrow(),
+                                *orientation.borrow(),
+                                chain.level_for_scale(minimap_scale),
+                                spread);
+                        }
                     });
                 }
             }
@@ -89,7 +418,22 @@ impl MyDisplay {
             let mut widget = glut_win.clone();
             let m_v = movement_vec.clone();
             let vvc = s_vc.clone();
+            let crossfade = s_crossfade.clone();
             move |_| {
+                if vvc.borrow_mut().step_smooth_zoom() {
+                    widget.redraw();
+                }
+
+                let fading = crossfade.borrow().as_ref().map(|fade| fade.progress() < 1.);
+                match fading {
+                    Some(true) => widget.redraw(),
+                    Some(false) => {
+                        *crossfade.borrow_mut() = None;
+                        widget.redraw();
+                    },
+                    None => {},
+                }
+
                 if m_v.borrow().x != 0. || m_v.borrow().y != 0. {
                     //following four lines: if scrolling away from an edge with a centerpos beyond whats a visible change, start from the visible position
                     if m_v.borrow().y < 0. && *vvc.borrow().s_centerpos_y.borrow() > vvc.borrow().max_visible_ypos {*vvc.borrow().s_centerpos_y.borrow_mut() = vvc.borrow().max_visible_ypos}
@@ -113,7 +457,14 @@ impl MyDisplay {
         glut_win.handle( {
             let mut click_coords = (0, 0);
             let mut start_pos = (0., 0.);
+            let mut dragging_wipe = false;
+            let mut dragging_minimap = false;
+            let mut selecting = false;
             let vvc = s_vc.clone();
+            let compare = s_compare.clone();
+            let wipe = s_wipe.clone();
+            let zoom_modifier = s_zoom_modifier.clone();
+            let minimap = s_minimap.clone();
 
             move |widget, event| {
             match event {
@@ -127,8 +478,32 @@ impl MyDisplay {
                 },
 
                 Event::Push => {
+                    if event_button() == MOUSE_BACK || event_button() == MOUSE_FORWARD {
+                        tx.send(if event_button() == MOUSE_BACK { Message::GoBack } else { Message::GoForward });
+                        return true;
+                    }
+                    tx.send(Message::UserInput);
                     if event_button() == 1 { button1_down = true}
                     click_coords = app::event_coords();
+                    let wipe_x = *wipe.borrow() * widget.width() as f32;
+                    if *compare.borrow() == CompareMode::Wipe && (click_coords.0 as f32 - wipe_x).abs() < WIPE_GRAB_DISTANCE {
+                        dragging_wipe = true;
+                        return true;
+                    }
+                    if event_button() == 1 && app::is_event_shift() {
+                        selecting = vvc.borrow_mut().start_selection(click_coords.0, click_coords.1);
+                        widget.redraw();
+                        return true;
+                    }
+                    if vvc.borrow_mut().clear_selection() {
+                        tx.send(Message::SelectionChanged(None));
+                        widget.redraw();
+                    }
+                    if *minimap.borrow() && vvc.borrow_mut().pan_to_minimap(click_coords.0, click_coords.1, false) {
+                        dragging_minimap = true;
+                        widget.redraw();
+                        return true;
+                    }
                     //following four lines: clicking the image makes it possible to begin dragging with a visible change, move these to event::drag as single fire??
                     if *vvc.borrow().s_centerpos_y.borrow() > vvc.borrow().max_visible_ypos {*vvc.borrow().s_centerpos_y.borrow_mut() = vvc.borrow().max_visible_ypos}
                     if *vvc.borrow().s_centerpos_y.borrow() < vvc.borrow().min_visible_ypos {*vvc.borrow().s_centerpos_y.borrow_mut() = vvc.borrow().min_visible_ypos}
@@ -142,6 +517,24 @@ impl MyDisplay {
                 }
 
                 Event::Drag => {
+                    if dragging_wipe {
+                        *wipe.borrow_mut() = (app::event_coords().0 as f32 / widget.width() as f32).clamp(0., 1.);
+                        widget.redraw();
+                        return true;
+                    }
+                    if selecting {
+                        let (x, y) = app::event_coords();
+                        vvc.borrow_mut().extend_selection(x, y);
+                        tx.send(Message::SelectionChanged(vvc.borrow().selection_source_rect()));
+                        widget.redraw();
+                        return true;
+                    }
+                    if dragging_minimap {
+                        let (x, y) = app::event_coords();
+                        vvc.borrow_mut().pan_to_minimap(x, y, true);
+                        widget.redraw();
+                        return true;
+                    }
                     let dx = app::event_coords().0 - click_coords.0;
                     let dy = app::event_coords().1 - click_coords.1;
                     //images drawn as quards have reversed coordinates 
@@ -152,19 +545,39 @@ impl MyDisplay {
                     true
                 }
 
+                Event::Enter => {
+                    true //needed to get move events
+                }
+
+                Event::Move => {
+                    let (x, y) = app::event_coords();
+                    tx.send(Message::PixelAt(vvc.borrow().source_point(x, y)));
+                    true
+                }
+
+                Event::Leave => {
+                    tx.send(Message::PixelAt(None));
+                    true
+                }
+
                 Event::Released => {
                     if event_button() == 1 { button1_down = false}
+                    dragging_wipe = false;
+                    dragging_minimap = false;
+                    selecting = false;
                     true
                 }
 
                 Event::MouseWheel => {
-                    if button1_down {
-                        if event_dy_value() > 0 {
-                            vvc.borrow_mut().zoom_out();
-                            widget.redraw();
-                        } else if event_dy_value() < 0 {
-                            vvc.borrow_mut().zoom_in();
-                            widget.redraw();
+                    tx.send(Message::UserInput);
+                    if button1_down || zoom_modifier.borrow().is_held() {
+                        //shift turns the wheel sideways on some systems
+                        let amount = if event_dy_value() != 0 { event_dy_value() } else { event_dx_value() };
+                        let (x, y) = app::event_coords();
+                        if amount > 0 {
+                            vvc.borrow_mut().smooth_zoom(1. / WHEEL_ZOOM_STEP, x, y);
+                        } else if amount < 0 {
+                            vvc.borrow_mut().smooth_zoom(WHEEL_ZOOM_STEP, x, y);
                         }
                     } else {
                         if event_dy_value() > 0 {
@@ -178,6 +591,17 @@ impl MyDisplay {
                     true
                 }
 
+                Event::ZoomGesture => {
+                    //pinching, the magnification comes in thousandths
+                    let factor = 1. + event_dy_value() as f32 / 1000.;
+                    if factor > 0. {
+                        let (x, y) = app::event_coords();
+                        vvc.borrow_mut().zoom_at(factor, x, y);
+                        widget.redraw();
+                    }
+                    true
+                }
+
                 Event::KeyUp => {
                     match event_key() {
                         Key::Up | Key::Down | Key::Left | Key::Right => {
@@ -195,7 +619,15 @@ impl MyDisplay {
                 }
 
                 Event::KeyDown => {
+                    if !matches!(event_key().to_char(), Some('+') | Some('-') | Some(' ')) { //slideshow controls
+                        tx.send(Message::UserInput);
+                    }
                     match event_key() {
+                        Key::Left | Key::Right if app::is_event_alt() => { //back and forward through visited listings
+                            tx.send(if event_key() == Key::Left { Message::GoBack } else { Message::GoForward });
+                            true
+                        },
+
                         Key::Up | Key::Down | Key::Left | Key::Right => {
                             if !event_key_down(Key::Up)
                                 && !event_key_down(Key::Down)
@@ -253,7 +685,7 @@ impl MyDisplay {
                             true
                         },
 
-                        Key::Delete => {
+                        Key::Delete if !app::is_event_ctrl() => { //ctrl+delete moves to the trash from the menu
                             vvc.borrow_mut().zoom_fit_to_window();
                             widget.redraw();
                             true
@@ -283,7 +715,22 @@ impl MyDisplay {
                             if let Some(cha) = event_key().to_char() { //can't seem to match '/'
                                 match cha {
                                     ' ' => {
-                                        println!("/");
+                                        tx.send(Message::SlideshowPause);
+                                        true
+                                    }
+
+                                    '+' => { //faster, a shorter interval
+                                        tx.send(Message::SlideshowFaster);
+                                        true
+                                    }
+
+                                    '-' => {
+                                        tx.send(Message::SlideshowSlower);
+                                        true
+                                    }
+
+                                    'c' if !app::is_event_ctrl() => { //ctrl+c copies the image from the menu
+                                        tx.send(Message::CopyPixel);
                                         true
                                     }
 
@@ -308,10 +755,150 @@ impl MyDisplay {
         Self {
             glut_win,
             s_displaying_image,
+            s_second_image,
+            s_spread,
+            s_compare,
+            s_difference,
+            s_wipe,
+            s_flicker_second,
+            s_flicker_timeout: Rc::new(RefCell::new(None)),
+            s_zoom_modifier,
+            s_crossfade,
+            s_animation,
+            s_animation_timeout: Rc::new(RefCell::new(None)),
+            s_histogram,
+            s_minimap,
             s_vc,
         }
     }
 
+    ///starts cycling the frames, or stops the running animation with none
+    pub fn set_animation(&mut self, animation: Option<Animation>) {
+        if let Some(handle) = self.s_animation_timeout.borrow_mut().take() {
+            if app::has_timeout3(handle) {
+                app::remove_timeout3(handle);
+            }
+        }
+
+        let first_delay = animation.as_ref().map(|animation| animation.delay());
+        *self.s_animation.borrow_mut() = animation;
+
+        if let Some(delay) = first_delay {
+            let s_animation = self.s_animation.clone();
+            let mut widget = self.glut_win.clone();
+            let handle = app::add_timeout3(delay, move |handle| {
+                let next_delay = s_animation.borrow_mut().as_mut().map(|animation| animation.advance());
+                if let Some(delay) = next_delay {
+                    widget.redraw();
+                    app::repeat_timeout3(delay, handle);
+                }
+            });
+            *self.s_animation_timeout.borrow_mut() = Some(handle);
+        }
+    }
+
+    ///shows the histogram overlay, none hides it
+    pub fn set_histogram(&mut self, histogram: Option<Histogram>) {
+        *self.s_histogram.borrow_mut() = histogram;
+        self.glut_win.redraw();
+    }
+
+    ///swaps the texture of the displayed image without touching the view settings
+    pub fn replace_image(&mut self, chain: MipChain) {
+        *self.s_displaying_image.borrow_mut() = Some(chain);
+        self.s_vc.borrow_mut().update_view_settings();
+        self.glut_win.redraw();
+    }
+
+    ///keeps drawing the current image while the next one fades in over it
+    pub fn start_crossfade(&mut self) {
+        let vc = self.s_vc.borrow();
+        let fade = self.s_displaying_image.borrow().as_ref().map(|chain| Crossfade {
+            chain: chain.clone(),
+            position: vc.s_position_in_vp.borrow().clone(),
+            coords: vc.s_image_coords.borrow().clone(),
+            orientation: *vc.s_orientation.borrow(),
+            scale: *vc.s_texture_scale.borrow(),
+            start: Instant::now(),
+        });
+        *self.s_crossfade.borrow_mut() = fade;
+    }
+
+    pub fn set_second_image(&mut self, chain: Option<MipChain>) {
+        *self.s_second_image.borrow_mut() = chain;
+        self.s_vc.borrow_mut().update_view_settings();
+        self.glut_win.redraw();
+    }
+
+    pub fn spread_mode(&self) -> SpreadMode {
+        *self.s_spread.borrow()
+    }
+
+    pub fn set_spread_mode(&mut self, mode: SpreadMode) {
+        *self.s_spread.borrow_mut() = mode;
+        self.s_vc.borrow_mut().update_view_settings();
+        self.glut_win.redraw();
+    }
+
+    pub fn compare_mode(&self) -> CompareMode {
+        *self.s_compare.borrow()
+    }
+
+    pub fn set_compare_mode(&mut self, mode: CompareMode) {
+        *self.s_compare.borrow_mut() = mode;
+        self.s_vc.borrow_mut().update_view_settings();
+        self.glut_win.redraw();
+    }
+
+    ///the difference image drawn in difference mode
+    pub fn set_difference(&mut self, chain: Option<MipChain>) {
+        *self.s_difference.borrow_mut() = chain;
+        self.glut_win.redraw();
+    }
+
+    ///switches between the two images in flicker mode, returns true when the second is shown
+    pub fn flicker(&mut self) -> bool {
+        let second = !*self.s_flicker_second.borrow();
+        *self.s_flicker_second.borrow_mut() = second;
+        self.glut_win.redraw();
+        second
+    }
+
+    pub fn is_flicker_timer_running(&self) -> bool {
+        self.s_flicker_timeout.borrow().is_some()
+    }
+
+    ///flickers on its own until stopped
+    pub fn set_flicker_timer(&mut self, running: bool) {
+        if let Some(handle) = self.s_flicker_timeout.borrow_mut().take() {
+            if app::has_timeout3(handle) {
+                app::remove_timeout3(handle);
+            }
+        }
+        if running {
+            let flicker_second = self.s_flicker_second.clone();
+            let mut widget = self.glut_win.clone();
+            let handle = app::add_timeout3(FLICKER_SECONDS, move |handle| {
+                let second = !*flicker_second.borrow();
+                *flicker_second.borrow_mut() = second;
+                widget.redraw();
+                app::repeat_timeout3(FLICKER_SECONDS, handle);
+            });
+            *self.s_flicker_timeout.borrow_mut() = Some(handle);
+        }
+    }
+
+    ///the thumbnail overlay with the visible part marked
+    pub fn toggle_minimap(&mut self) {
+        let shown = !*self.s_minimap.borrow();
+        *self.s_minimap.borrow_mut() = shown;
+        self.glut_win.redraw();
+    }
+
+    pub fn set_zoom_modifier(&mut self, modifier: ZoomModifier) {
+        *self.s_zoom_modifier.borrow_mut() = modifier;
+    }
+
     pub fn put_vs(&mut self, vs: ViewSettings) {
         self.s_vc.borrow_mut().keep_ar = vs.keep_ar;
         self.s_vc.borrow_mut().fit_to_window = vs.fit_to_window;
@@ -319,6 +906,7 @@ impl MyDisplay {
         self.s_vc.borrow_mut().zoom_lvl_y = vs.zoom_lvl_y;
         *self.s_vc.borrow().s_centerpos_x.borrow_mut() = vs.centerpos_x;
         *self.s_vc.borrow().s_centerpos_y.borrow_mut() = vs.centerpos_y;
+        *self.s_vc.borrow().s_orientation.borrow_mut() = vs.orientation;
     }
 
     pub fn get_vs(&self) -> ViewSettings {
@@ -329,7 +917,171 @@ impl MyDisplay {
             zoom_lvl_y: self.s_vc.borrow().zoom_lvl_y,
             centerpos_x: *self.s_vc.borrow().s_centerpos_x.borrow(),
             centerpos_y: *self.s_vc.borrow().s_centerpos_y.borrow(),
+            orientation: *self.s_vc.borrow().s_orientation.borrow(),
+        }
+    }
+}
+
+///draws the visible part of the image, coords are in screen orientation and get mapped to the source texture
+fn draw_oriented(graphics: &mut Graphics2D, position: &Rectangle, coords: &Rectangle, orientation: Orientation, handle: &ImageHandle, tint: Color) {
+    let tl = *position.top_left();
+    let br = *position.bottom_right();
+    let positions = [tl, Vector2::new(br.x, tl.y), br, Vector2::new(tl.x, br.y)]; //clockwise
+
+    let c_tl = *coords.top_left();
+    let c_br = *coords.bottom_right();
+    let corners = [(c_tl.x, c_tl.y), (c_br.x, c_tl.y), (c_br.x, c_br.y), (c_tl.x, c_br.y)];
+    let source_coords = corners.map(|(x, y)| {
+        let (sx, sy) = orientation.to_source(x, y);
+        Vector2::new(sx, sy)
+    });
+
+    graphics.draw_quad_image_tinted_four_color(positions, [tint; 4], source_coords, handle);
+}
+
+///rgb and luminance curves in the lower left corner, one screen pixel pr bin
+fn draw_histogram(graphics: &mut Graphics2D, histogram: &Histogram, window_h: f32) {
+    let left = HISTOGRAM_MARGIN;
+    let bottom = window_h - HISTOGRAM_MARGIN;
+    let top = bottom - HISTOGRAM_HEIGHT;
+    graphics.draw_rectangle(
+        Rectangle::new(Vector2::new(left - 2., top - 2.), Vector2::new(left + BINS as f32 + 2., bottom + 2.)),
+        Color::from_rgba(0., 0., 0., 0.6));
+
+    if histogram.peak == 0 {
+        return;
+    }
+    let colors = [
+        Color::from_rgba(1., 0.2, 0.2, 0.6),
+        Color::from_rgba(0.2, 1., 0.2, 0.6),
+        Color::from_rgba(0.3, 0.3, 1., 0.6),
+        Color::from_rgba(1., 1., 1., 0.8),
+    ];
+    for (bins, color) in histogram.bins.iter().zip(colors) {
+        for (n, &count) in bins.iter().enumerate() {
+            if count > 0 {
+                let x = left + n as f32 + 0.5;
+                let height = (count as f32 / histogram.peak as f32 * HISTOGRAM_HEIGHT).max(1.);
+                graphics.draw_line(Vector2::new(x, bottom), Vector2::new(x, bottom - height), 1., color);
+            }
+        }
+    }
+}
+
+///the whole image in the upper right corner, scaled to fit the minimap size
+fn minimap_rect(window_w: f32, position: &Rectangle, coords: &Rectangle) -> Rectangle {
+    let full_w = position.width() / coords.width();
+    let full_h = position.height() / coords.height();
+    let scale = MINIMAP_SIZE / full_w.max(full_h);
+    let right = window_w - MINIMAP_MARGIN;
+    Rectangle::new(
+        Vector2::new(right - full_w * scale, MINIMAP_MARGIN),
+        Vector2::new(right, MINIMAP_MARGIN + full_h * scale))
+}
+
+///thumbnail of the image or spread with a frame around the visible part
+fn draw_minimap(graphics: &mut Graphics2D, rect: &Rectangle, coords: &Rectangle, orientation: Orientation, primary: &ImageHandle, spread: Option<(&SpreadGeometry, &ImageHandle)>) {
+    graphics.draw_rectangle(
+        Rectangle::new(*rect.top_left() - Vector2::new(2., 2.), *rect.bottom_right() + Vector2::new(2., 2.)),
+        Color::from_rgba(0., 0., 0., 0.6));
+    let whole = Rectangle::new(Vector2::new(0., 0.), Vector2::new(1., 1.));
+    match spread {
+        Some((geometry, second)) => draw_spread(graphics, rect, &whole, orientation, geometry, primary, second, Color::WHITE),
+        None => draw_oriented(graphics, rect, &whole, orientation, primary, Color::WHITE),
+    }
+
+    let to_screen = |u: f32, v: f32| Vector2::new(
+        rect.top_left().x + u * rect.width(),
+        rect.top_left().y + v * rect.height());
+    let corners = [
+        to_screen(coords.top_left().x, coords.top_left().y),
+        to_screen(coords.bottom_right().x, coords.top_left().y),
+        to_screen(coords.bottom_right().x, coords.bottom_right().y),
+        to_screen(coords.top_left().x, coords.bottom_right().y),
+    ];
+    for n in 0..4 {
+        graphics.draw_line(corners[n], corners[(n + 1) % 4], 1., Color::YELLOW);
+    }
+}
+
+///black and white outline of the selected part, visible on any image
+fn draw_selection(graphics: &mut Graphics2D, position: &Rectangle, coords: &Rectangle, selection: (f32, f32, f32, f32)) {
+    let to_screen = |u: f32, v: f32| Vector2::new(
+        position.top_left().x + (u - coords.top_left().x) / coords.width() * position.width(),
+        position.top_left().y + (v - coords.top_left().y) / coords.height() * position.height());
+    let (u0, v0, u1, v1) = selection;
+    let corners = [to_screen(u0, v0), to_screen(u1, v0), to_screen(u1, v1), to_screen(u0, v1)];
+    for n in 0..4 {
+        graphics.draw_line(corners[n], corners[(n + 1) % 4], 3., Color::BLACK);
+        graphics.draw_line(corners[n], corners[(n + 1) % 4], 1., Color::WHITE);
+    }
+}
+
+///draws the part of the image between two x positions on screen
+fn draw_clipped(graphics: &mut Graphics2D, position: &Rectangle, coords: &Rectangle, orientation: Orientation, handle: &ImageHandle, x0: f32, x1: f32, tint: Color) {
+    let left = position.top_left().x.max(x0);
+    let right = position.bottom_right().x.min(x1);
+    if right <= left {
+        return;
+    }
+    let to_u = |x: f32| coords.top_left().x + (x - position.top_left().x) / position.width() * coords.width();
+    let clipped_position = Rectangle::new(
+        Vector2::new(left, position.top_left().y),
+        Vector2::new(right, position.bottom_right().y));
+    let clipped_coords = Rectangle::new(
+        Vector2::new(to_u(left), coords.top_left().y),
+        Vector2::new(to_u(right), coords.bottom_right().y));
+    draw_oriented(graphics, &clipped_position, &clipped_coords, orientation, handle, tint);
+}
+
+///the second image is drawn with the same view in the right half of the window
+///the second image is laid out from its own size, at the zoom of the first and with the same relative point in the middle,
+///sizes are the logical sizes of both in screen orientation
+fn draw_side_by_side(graphics: &mut Graphics2D, position: &Rectangle, coords: &Rectangle, orientation: Orientation, primary: &ImageHandle, second: &ImageHandle, sizes: [(f32, f32); 2], half_w: f32, window_h: f32, tint: Color) {
+    draw_oriented(graphics, position, coords, orientation, primary, tint);
+
+    let [(p_w, p_h), (s_w, s_h)] = sizes;
+    let zoom_x = position.width() / (coords.width() * p_w); //screen pixels per image pixel
+    let zoom_y = position.height() / (coords.height() * p_h);
+    let center_u = (coords.top_left().x + coords.bottom_right().x) / 2.;
+    let center_v = (coords.top_left().y + coords.bottom_right().y) / 2.;
+    let center_x = (position.top_left().x + position.bottom_right().x) / 2. + half_w;
+    let center_y = (position.top_left().y + position.bottom_right().y) / 2.;
+
+    //the whole second image on screen, then cut to the right half of the window
+    let (left, top) = (center_x - center_u * s_w * zoom_x, center_y - center_v * s_h * zoom_y);
+    let (right, bottom) = (left + s_w * zoom_x, top + s_h * zoom_y);
+    let (x0, y0) = (left.max(half_w), top.max(0.));
+    let (x1, y1) = (right.min(2. * half_w), bottom.min(window_h));
+    if x1 > x0 && y1 > y0 {
+        let second_position = Rectangle::new(Vector2::new(x0, y0), Vector2::new(x1, y1));
+        let second_coords = Rectangle::new(
+            Vector2::new((x0 - left) / (right - left), (y0 - top) / (bottom - top)),
+            Vector2::new((x1 - left) / (right - left), (y1 - top) / (bottom - top)));
+        draw_oriented(graphics, &second_position, &second_coords, orientation, second, tint);
+    }
+    graphics.draw_line(Vector2::new(half_w, 0.), Vector2::new(half_w, window_h), 1., Color::GRAY);
+}
+
+///splits the visible part of a spread between the two pages and draws each of them
+fn draw_spread(graphics: &mut Graphics2D, position: &Rectangle, coords: &Rectangle, orientation: Orientation, geometry: &SpreadGeometry, primary: &ImageHandle, second: &ImageHandle, tint: Color) {
+    let (left, right) = if geometry.left_is_primary { (primary, second) } else { (second, primary) };
+    let pages = [(left, 0., geometry.split), (right, geometry.split, 1.)];
+    let to_screen_x = |u: f32| position.top_left().x + (u - coords.top_left().x) / coords.width() * position.width();
+
+    for (handle, start, end) in pages {
+        let u0 = coords.top_left().x.max(start);
+        let u1 = coords.bottom_right().x.min(end);
+        if u1 <= u0 {
+            continue; //page is scrolled out of view
         }
+        let page_position = Rectangle::new(
+            Vector2::new(to_screen_x(u0), position.top_left().y),
+            Vector2::new(to_screen_x(u1), position.bottom_right().y));
+        let page_coords = Rectangle::new(
+            Vector2::new((u0 - start) / (end - start), coords.top_left().y),
+            Vector2::new((u1 - start) / (end - start), coords.bottom_right().y));
+        draw_oriented(graphics, &page_position, &page_coords, orientation, handle, tint);
     }
 }
 
@@ -338,7 +1090,13 @@ pub struct ViewConfig {
     s_renderer: Rc<RefCell<speedy2d::GLRenderer>>,
     s_position_in_vp: Rc<RefCell<Rectangle>>,
     s_image_coords: Rc<RefCell<Rectangle>>,
-    s_displaying_image: Rc<RefCell<Option<ImageHandle>>>,
+    s_displaying_image: Rc<RefCell<Option<MipChain>>>,
+    s_texture_scale: Rc<RefCell<f32>>, //screen pixels per image pixel, picks the mip level
+    s_second_image: Rc<RefCell<Option<MipChain>>>,
+    s_spread: Rc<RefCell<SpreadMode>>,
+    s_spread_geometry: Rc<RefCell<Option<SpreadGeometry>>>,
+    s_compare: Rc<RefCell<CompareMode>>,
+    s_selection: Rc<RefCell<Option<(f32, f32, f32, f32)>>>, //anchor and dragged corner in normalized screen oriented coordinates
     zoom_lvl_x_effective: f32,
     zoom_lvl_y_effective: f32,
     onepix_modifier_x: f32,
@@ -353,6 +1111,9 @@ pub struct ViewConfig {
     zoom_lvl_y: f32,
     s_centerpos_x: Rc<RefCell<f32>>,
     s_centerpos_y: Rc<RefCell<f32>>,
+    s_orientation: Rc<RefCell<Orientation>>,
+    pending_zoom: f32, //natural log of the zoom factor still to apply
+    zoom_anchor: (i32, i32), //window position that stays on the same image point while zooming
     tx: app::Sender<Message>,
 }
 
@@ -373,41 +1134,179 @@ impl ViewConfig {
         }
     }
     
-    pub fn upload_image(&self, image: DynamicImage) -> Result<ImageHandle, Box<dyn Error>> { //make this result, incase upload fails
+    ///two pages are drawn, next and previous should step past both
+    pub fn showing_spread(&self) -> bool {
+        self.s_spread_geometry.borrow().is_some()
+    }
+
+    ///x, y, width and height of the visible part in normalized source coordinates, only the first page of a spread
+    pub fn visible_source_rect(&self) -> (f32, f32, f32, f32) {
+        let coords = self.s_image_coords.borrow();
+        self.to_source_rect(coords.top_left().x, coords.top_left().y, coords.bottom_right().x, coords.bottom_right().y)
+    }
+
+    ///the selection as x, y, width and height in normalized source coordinates, none when empty
+    pub fn selection_source_rect(&self) -> Option<(f32, f32, f32, f32)> {
+        let (u0, v0, u1, v1) = (*self.s_selection.borrow())?;
+        let rect = self.to_source_rect(u0.min(u1), v0.min(v1), u0.max(u1), v0.max(v1));
+        if rect.2 > 0. && rect.3 > 0. { Some(rect) } else { None }
+    }
+
+    ///anchors a new selection at a window position, false outside the image
+    pub fn start_selection(&mut self, x: i32, y: i32) -> bool {
+        let point = self.view_point(x, y);
+        *self.s_selection.borrow_mut() = point.map(|(u, v)| (u, v, u, v));
+        point.is_some()
+    }
+
+    pub fn extend_selection(&mut self, x: i32, y: i32) {
+        let point = self.view_point(x, y);
+        if let (Some(selection), Some((u, v))) = (self.s_selection.borrow_mut().as_mut(), point) {
+            (selection.2, selection.3) = (u, v);
+        }
+    }
+
+    ///returns true if there was a selection
+    pub fn clear_selection(&mut self) -> bool {
+        self.s_selection.borrow_mut().take().is_some()
+    }
+
+    ///maps a rectangle of the laid out image to the source, only the part on the first page of a spread
+    fn to_source_rect(&self, mut u0: f32, v0: f32, mut u1: f32, v1: f32) -> (f32, f32, f32, f32) {
+        if let Some(geometry) = self.s_spread_geometry.borrow().as_ref() {
+            let (start, end) = if geometry.left_is_primary { (0., geometry.split) } else { (geometry.split, 1.) };
+            u0 = ((u0.max(start) - start) / (end - start)).clamp(0., 1.);
+            u1 = ((u1.min(end) - start) / (end - start)).clamp(u0, 1.);
+        }
+
+        let orientation = *self.s_orientation.borrow();
+        let corners = [(u0, v0), (u1, v0), (u1, v1), (u0, v1)].map(|(x, y)| orientation.to_source(x, y));
+        let min_x = corners.iter().map(|c| c.0).fold(1f32, f32::min);
+        let min_y = corners.iter().map(|c| c.1).fold(1f32, f32::min);
+        let max_x = corners.iter().map(|c| c.0).fold(0f32, f32::max);
+        let max_y = corners.iter().map(|c| c.1).fold(0f32, f32::max);
+        (min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    ///first visible position and visible size along x and y, normalized in screen orientation
+    pub fn visible_range(&self) -> ((f32, f32), (f32, f32)) {
+        let coords = self.s_image_coords.borrow();
+        ((coords.top_left().x, coords.width()), (coords.top_left().y, coords.height()))
+    }
+
+    ///moves the view so its visible part starts at the given positions, none keeps that axis
+    pub fn scroll_to(&mut self, first_x: Option<f32>, first_y: Option<f32>) {
+        let ((_, w), (_, h)) = self.visible_range();
+        if let Some(x) = first_x {
+            *self.s_centerpos_x.borrow_mut() = (x + w / 2.).clamp(self.min_visible_xpos, self.max_visible_xpos);
+        }
+        if let Some(y) = first_y {
+            *self.s_centerpos_y.borrow_mut() = (y + h / 2.).clamp(self.min_visible_ypos, self.max_visible_ypos);
+        }
+        self.update_view_settings();
+    }
+
+    ///centers the view on the minimap point under a window position, outside points only count while dragging
+    pub fn pan_to_minimap(&mut self, x: i32, y: i32, dragging: bool) -> bool {
+        if self.s_displaying_image.borrow().is_none() {
+            return false;
+        }
+        let rect = minimap_rect(self.glut_win.width() as f32, &self.s_position_in_vp.borrow(), &self.s_image_coords.borrow());
+        let u = (x as f32 - rect.top_left().x) / rect.width();
+        let v = (y as f32 - rect.top_left().y) / rect.height();
+        if !dragging && (!(0. ..=1.).contains(&u) || !(0. ..=1.).contains(&v)) {
+            return false;
+        }
+        *self.s_centerpos_x.borrow_mut() = u.clamp(self.min_visible_xpos, self.max_visible_xpos);
+        *self.s_centerpos_y.borrow_mut() = v.clamp(self.min_visible_ypos, self.max_visible_ypos);
+        self.update_view_settings();
+        true
+    }
+
+    ///normalized source coordinates under a point in the window, none outside the image or on the second page
+    pub fn source_point(&self, x: i32, y: i32) -> Option<(f32, f32)> {
+        self.s_displaying_image.borrow().as_ref()?;
+        let position = self.s_position_in_vp.borrow();
+        let coords = self.s_image_coords.borrow();
+
+        let mut x = x as f32;
+        if *self.s_compare.borrow() == CompareMode::SideBySide && x >= self.glut_win.width() as f32 / 2. {
+            x -= self.glut_win.width() as f32 / 2.; //same point in the second image
+        }
+        let fx = (x - position.top_left().x) / position.width();
+        let fy = (y as f32 - position.top_left().y) / position.height();
+        if !(0. ..1.).contains(&fx) || !(0. ..1.).contains(&fy) {
+            return None;
+        }
+        let mut u = coords.top_left().x + fx * coords.width();
+        let v = coords.top_left().y + fy * coords.height();
+
+        if let Some(geometry) = self.s_spread_geometry.borrow().as_ref() {
+            let (start, end) = if geometry.left_is_primary { (0., geometry.split) } else { (geometry.split, 1.) };
+            if u < start || u >= end {
+                return None;
+            }
+            u = (u - start) / (end - start);
+        }
+        Some(self.s_orientation.borrow().to_source(u, v))
+    }
+
+    ///uploads the image and a chain of downscaled copies for zooming out without aliasing
+    pub fn upload_image(&self, images: &MipImages) -> Result<MipChain, Box<dyn Error>> {
         println!("uploading an image");
-        match image {
-            DynamicImage::ImageRgb8(image_buffer) => {
-                Ok(self.s_renderer.borrow_mut().create_image_from_raw_pixels(
-                speedy2d::image::ImageDataType::RGB, 
-                speedy2d::image::ImageSmoothingMode::Linear, //set smoothingmode from ui
-                Vector2::new(image_buffer.width(), image_buffer.height()),
-                image_buffer.as_bytes())?)
-            },
-
-            DynamicImage::ImageRgba8(image_buffer) => {
-                Ok(self.s_renderer.borrow_mut().create_image_from_raw_pixels(
-                speedy2d::image::ImageDataType::RGBA, 
-                speedy2d::image::ImageSmoothingMode::Linear,
-                Vector2::new(image_buffer.width(), image_buffer.height()),
-                image_buffer.as_bytes())?)
-            },
-
-            _ => {
-                Ok(self.s_renderer.borrow_mut().create_image_from_raw_pixels(
-                speedy2d::image::ImageDataType::RGB, 
-                speedy2d::image::ImageSmoothingMode::Linear,
-                Vector2::new(image.width(), image.height()),
-                image.into_rgb8().as_bytes())?)
-            },
+        let base = images.base(); //converted on the decoding thread
+        let mut levels = vec![self.upload_level(base)?];
+        let mut data_size = base.as_bytes().len();
+        for level in &images.smaller {
+            levels.push(self.upload_level(level)?);
+            data_size += level.as_bytes().len();
         }
+
+        let logical_size = (levels[0].size().x as f32, levels[0].size().y as f32);
+        Ok(MipChain { levels, data_size, logical_size, raster_scale: 1., vector: false })
+    }
+
+    fn upload_level(&self, image: &DynamicImage) -> Result<ImageHandle, Box<dyn Error>> {
+        let data_type = match image {
+            DynamicImage::ImageRgba8(_) => speedy2d::image::ImageDataType::RGBA,
+            _ => speedy2d::image::ImageDataType::RGB,
+        };
+
+        Ok(self.s_renderer.borrow_mut().create_image_from_raw_pixels(
+            data_type,
+            speedy2d::image::ImageSmoothingMode::Linear, //set smoothingmode from ui
+            Vector2::new(image.width(), image.height()),
+            image.as_bytes())?)
     }
 
     pub fn update_view_settings(&mut self) {
-        if let Some(handle) = self.s_displaying_image.borrow().as_ref() {
-            let image_size= handle.size();
-            let i_w = image_size.x as f32;
-            let i_h = image_size.y as f32;
-            let w_w = self.glut_win.width() as f32;
+        if let Some(chain) = self.s_displaying_image.borrow().as_ref() {
+            //everything below is in screen orientation, the source is only used when drawing
+            let transposed = self.s_orientation.borrow().is_transposed();
+            let oriented_size = |chain: &MipChain| {
+                let (w, h) = chain.logical_size();
+                if transposed { (h, w) } else { (w, h) }
+            };
+            let (mut i_w, i_h) = oriented_size(chain);
+
+            //two pages are laid out as one image, with the second page scaled to the height of the first
+            let mut geometry = None;
+            let comparing = *self.s_compare.borrow() != CompareMode::Off;
+            if *self.s_spread.borrow() != SpreadMode::Single && !comparing {
+                if let Some(second) = self.s_second_image.borrow().as_ref() {
+                    let (b_w, b_h) = oriented_size(second);
+                    if i_w <= i_h && b_w <= b_h { //double page spreads are shown alone
+                        let b_w_scaled = b_w * i_h / b_h;
+                        let left_is_primary = *self.s_spread.borrow() == SpreadMode::LeftToRight;
+                        let split = if left_is_primary { i_w / (i_w + b_w_scaled) } else { b_w_scaled / (i_w + b_w_scaled) };
+                        geometry = Some(SpreadGeometry { left_is_primary, split, second_scale: i_h / b_h });
+                        i_w += b_w_scaled;
+                    }
+                }
+            }
+            *self.s_spread_geometry.borrow_mut() = geometry;
+            //side by side images each get half of the window
+            let w_w = if *self.s_compare.borrow() == CompareMode::SideBySide { self.glut_win.width() as f32 / 2. } else { self.glut_win.width() as f32 };
             let w_h = self.glut_win.height() as f32;
             
             let image_ar = i_w/i_h;
@@ -459,6 +1358,19 @@ impl ViewConfig {
             //move all these calculations elsewhere?
             self.zoom_lvl_x_effective = new_i_w / i_w;
             self.zoom_lvl_y_effective = new_i_h / i_h;
+
+            //effective zoom is for the visible part, divide by it to get the drawn scale
+            *self.s_texture_scale.borrow_mut() = (self.zoom_lvl_x_effective / xzl).min(self.zoom_lvl_y_effective / yzl);
+
+            //vector images are rasterized again when zooming in past the texture resolution
+            let scale = (self.zoom_lvl_x_effective / xzl).max(self.zoom_lvl_y_effective / yzl);
+            if chain.vector && scale > chain.raster_scale * 1.01 {
+                let (w, h) = chain.logical_size();
+                let wanted = 2f32.powf(scale.log2().ceil()).min(MAX_VECTOR_TEXTURE_SIZE / w.max(h));
+                if wanted > chain.raster_scale {
+                    self.tx.send(Message::RasterizeVector(wanted));
+                }
+            }
             
             self.onepix_modifier_x = xzl/w_w;
             self.onepix_modifier_y = yzl/w_h;
@@ -531,11 +1443,94 @@ impl ViewConfig {
         self.update_view_settings();
     }
 
+    ///zooms and keeps the image point under the window position in place
+    pub fn zoom_at(&mut self, factor: f32, x: i32, y: i32) {
+        let before = self.view_point(x, y);
+        if self.fit_to_window {self.disable_fit_to_window();}
+        self.zoom_lvl_x *= factor;
+        self.zoom_lvl_y *= factor;
+        self.update_view_settings();
+
+        if let (Some((u, v)), Some((new_u, new_v))) = (before, self.view_point(x, y)) {
+            let center_x = (*self.s_centerpos_x.borrow() + u - new_u).clamp(self.min_visible_xpos, self.max_visible_xpos);
+            let center_y = (*self.s_centerpos_y.borrow() + v - new_v).clamp(self.min_visible_ypos, self.max_visible_ypos);
+            *self.s_centerpos_x.borrow_mut() = center_x;
+            *self.s_centerpos_y.borrow_mut() = center_y;
+            self.update_view_settings();
+        }
+    }
+
+    ///adds a zoom step that is spread over the next idle steps
+    pub fn smooth_zoom(&mut self, factor: f32, x: i32, y: i32) {
+        self.pending_zoom += factor.ln();
+        self.zoom_anchor = (x, y);
+    }
+
+    ///applies part of the pending zoom, returns true when the view changed
+    fn step_smooth_zoom(&mut self) -> bool {
+        if self.pending_zoom == 0. {
+            return false;
+        }
+        let step = if self.pending_zoom.abs() < SMOOTH_ZOOM_DONE { self.pending_zoom } else { self.pending_zoom * SMOOTH_ZOOM_RATE };
+        self.pending_zoom -= step;
+        self.zoom_at(step.exp(), self.zoom_anchor.0, self.zoom_anchor.1);
+        true
+    }
+
+    ///point of the laid out image under a window position, in normalized screen oriented coordinates
+    fn view_point(&self, x: i32, y: i32) -> Option<(f32, f32)> {
+        self.s_displaying_image.borrow().as_ref()?;
+        let position = self.s_position_in_vp.borrow();
+        let coords = self.s_image_coords.borrow();
+        let mut x = x as f32;
+        if *self.s_compare.borrow() == CompareMode::SideBySide && x >= self.glut_win.width() as f32 / 2. {
+            x -= self.glut_win.width() as f32 / 2.;
+        }
+        let fx = ((x - position.top_left().x) / position.width()).clamp(0., 1.);
+        let fy = ((y as f32 - position.top_left().y) / position.height()).clamp(0., 1.);
+        Some((coords.top_left().x + fx * coords.width(), coords.top_left().y + fy * coords.height()))
+    }
+
     pub fn zoom_fit_to_window(&mut self) {
         self.fit_to_window = true;
         self.update_view_settings();
     }
 
+    pub fn rotate_right(&mut self) {
+        self.s_orientation.borrow_mut().rotate_right();
+        //keep the same part of the image in the center
+        let (x, y) = (*self.s_centerpos_x.borrow(), *self.s_centerpos_y.borrow());
+        *self.s_centerpos_x.borrow_mut() = 1. - y;
+        *self.s_centerpos_y.borrow_mut() = x;
+        std::mem::swap(&mut self.zoom_lvl_x, &mut self.zoom_lvl_y);
+        self.update_view_settings();
+    }
+
+    pub fn rotate_left(&mut self) {
+        self.s_orientation.borrow_mut().rotate_left();
+        let (x, y) = (*self.s_centerpos_x.borrow(), *self.s_centerpos_y.borrow());
+        *self.s_centerpos_x.borrow_mut() = y;
+        *self.s_centerpos_y.borrow_mut() = 1. - x;
+        std::mem::swap(&mut self.zoom_lvl_x, &mut self.zoom_lvl_y);
+        self.update_view_settings();
+    }
+
+    pub fn flip_horizontal(&mut self) {
+        let flipped = !self.s_orientation.borrow().flip_h;
+        self.s_orientation.borrow_mut().flip_h = flipped;
+        let x = *self.s_centerpos_x.borrow();
+        *self.s_centerpos_x.borrow_mut() = 1. - x;
+        self.update_view_settings();
+    }
+
+    pub fn flip_vertical(&mut self) {
+        let flipped = !self.s_orientation.borrow().flip_v;
+        self.s_orientation.borrow_mut().flip_v = flipped;
+        let y = *self.s_centerpos_y.borrow();
+        *self.s_centerpos_y.borrow_mut() = 1. - y;
+        self.update_view_settings();
+    }
+
     pub fn zoom_1_to_1(&mut self) {
         self.zoom_lvl_x = 1.;
         self.zoom_lvl_y = 1.;
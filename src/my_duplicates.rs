@@ -0,0 +1,339 @@
+const DHASH_LIMIT: u32 = 10; //differing bits of 64 that still count as the same picture
+const PHASH_LIMIT: u32 = 12;
+const HASH_SIZE: u32 = 32; //grayscale thumbnail the dct of the phash is taken from
+const PROGRESS_EVERY: usize = 25;
+const WINDOW_W: i32 = 700;
+const WINDOW_H: i32 = 450;
+
+use std::{collections::hash_map::DefaultHasher, f32::consts::PI, hash::{Hash, Hasher}, panic::{self, AssertUnwindSafe}, path::{Path, PathBuf}, sync::{atomic::{AtomicUsize, Ordering}, mpsc, Mutex}, thread};
+
+use fltk::{app::Sender, browser::HoldBrowser, button::Button, frame::Frame, group::Flex, prelude::{BrowserExt, GroupExt, WidgetBase, WidgetExt, WindowExt}, window::Window};
+use image::{imageops::{self, FilterType}, DynamicImage, GrayImage};
+
+use crate::{my_model::MyModel, screen_center, Message};
+
+///an image that was read and hashed
+#[derive(Clone)]
+pub struct Candidate {
+    pub path: PathBuf,
+    pub file_size: u64,
+    pub dimensions: Option<(u32, u32)>, //none when it couldn't be decoded
+    exact: u64, //hash of the file data
+    perceptual: Option<(u64, u64)>, //dhash and phash
+}
+
+#[derive(Clone)]
+pub struct DuplicateGroup {
+    pub files: Vec<Candidate>, //largest first, the one to keep by default
+    pub identical: bool, //all files have the same data
+}
+
+///hashes all images below the folder, or in the archive, on worker threads and sends the groups found
+pub fn find_duplicates(root: PathBuf, tx: Sender<Message>) {
+    thread::spawn(move || {
+        let images = match MyModel::collect_images(&root) {
+            Ok(images) => images,
+            Err(e) => {
+                tx.send(Message::Info(format!("Finding duplicates failed: {e}")));
+                return;
+            },
+        };
+
+        let total = images.len();
+        let finished = AtomicUsize::new(0);
+        let candidates = Mutex::new(Vec::with_capacity(total));
+        let workers = thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        //one reader goes through the folder or the archive once, the workers decode and hash
+        let (sender, receiver) = mpsc::sync_channel::<(PathBuf, Vec<u8>)>(workers * 2);
+        let receiver = Mutex::new(receiver);
+        let (finished, candidates, receiver) = (&finished, &candidates, &receiver);
+        let read = thread::scope(|scope| {
+            for _ in 0..workers {
+                scope.spawn(move || {
+                    loop {
+                        let next = receiver.lock().expect("A hashing thread panicked.").recv(); //not held while hashing
+                        let Ok((path, data)) = next else { break };
+                        let candidate = hash_data(path, &data);
+                        candidates.lock().expect("A hashing thread panicked.").push(candidate);
+                        let done = finished.fetch_add(1, Ordering::Relaxed) + 1;
+                        if done % PROGRESS_EVERY == 0 {
+                            tx.send(Message::DuplicateProgress(done, total));
+                        }
+                    }
+                });
+            }
+            let read = MyModel::read_images(&root, &images, |path, data| {
+                let _ = sender.send((path, data));
+            });
+            drop(sender); //the workers stop when it is empty
+            read
+        });
+        if let Err(e) = read {
+            tx.send(Message::Info(format!("Not all images could be read: {e}")));
+        }
+
+        let groups = group(candidates.lock().map(|mut found| std::mem::take(&mut *found)).unwrap_or_default());
+        tx.send(Message::DuplicatesFound(groups, root));
+    });
+}
+
+fn hash_data(path: PathBuf, data: &[u8]) -> Candidate {
+    let mut hasher = DefaultHasher::new();
+    data.hash(&mut hasher);
+    //a broken file shouldn't take the whole search down
+    let image = panic::catch_unwind(AssertUnwindSafe(|| MyModel::decode_still(data, &path).ok())).ok().flatten();
+    Candidate {
+        dimensions: image.as_ref().map(|image| (image.width(), image.height())),
+        perceptual: image.as_ref().map(perceptual_hashes),
+        path,
+        file_size: data.len() as u64,
+        exact: hasher.finish(),
+    }
+}
+
+///dhash compares neighbouring pixels, phash the low frequencies of a dct
+fn perceptual_hashes(image: &DynamicImage) -> (u64, u64) {
+    let gray = image.thumbnail_exact(HASH_SIZE, HASH_SIZE).to_luma8();
+    (dhash(&gray), phash(&gray))
+}
+
+fn dhash(gray: &GrayImage) -> u64 {
+    let small = imageops::resize(gray, 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn phash(gray: &GrayImage) -> u64 {
+    let n = HASH_SIZE as usize;
+    let cosines: Vec<Vec<f32>> = (0..8)
+        .map(|k| (0..n).map(|x| ((2 * x + 1) as f32 * k as f32 * PI / (2 * n) as f32).cos()).collect())
+        .collect();
+
+    //only the 8 x 8 lowest frequencies are needed, rows first
+    let mut rows = vec![[0f32; 8]; n];
+    for (y, row) in rows.iter_mut().enumerate() {
+        for (u, coefficient) in row.iter_mut().enumerate() {
+            *coefficient = (0..n).map(|x| gray.get_pixel(x as u32, y as u32).0[0] as f32 * cosines[u][x]).sum();
+        }
+    }
+    let mut coefficients = [0f32; 64];
+    for (index, coefficient) in coefficients.iter_mut().enumerate() {
+        let (v, u) = (index / 8, index % 8);
+        *coefficient = rows.iter().zip(&cosines[v]).map(|(row, cosine)| row[u] * cosine).sum();
+    }
+
+    //the average brightness is left out of the median
+    let mut sorted = coefficients[1..].to_vec();
+    sorted.sort_by(|a, b| a.total_cmp(b));
+    let median = sorted[sorted.len() / 2];
+    coefficients.iter().fold(0u64, |hash, &c| (hash << 1) | (c > median) as u64)
+}
+
+///identical data, or both hashes close to those of the group's first file, puts images in the same group,
+///every file is compared with the first so similarity doesn't chain A to C through B
+fn group(mut candidates: Vec<Candidate>) -> Vec<DuplicateGroup> {
+    //the first file of a group is the one kept by default
+    candidates.sort_by(|a, b| {
+        let pixels = |c: &Candidate| c.dimensions.map(|(w, h)| w as u64 * h as u64).unwrap_or(0);
+        pixels(b).cmp(&pixels(a)).then(b.file_size.cmp(&a.file_size)).then(a.path.cmp(&b.path))
+    });
+
+    let mut grouped = vec![false; candidates.len()];
+    let mut groups = Vec::new();
+    for (i, first) in candidates.iter().enumerate() {
+        if grouped[i] {
+            continue;
+        }
+        let mut files = vec![first.clone()];
+        for (j, other) in candidates.iter().enumerate().skip(i + 1) {
+            if !grouped[j] && same_picture(first, other) {
+                grouped[j] = true;
+                files.push(other.clone());
+            }
+        }
+        if files.len() > 1 {
+            let identical = files.iter().all(|c| same_data(c, &files[0]));
+            groups.push(DuplicateGroup { files, identical });
+        }
+    }
+    groups.sort_by(|a, b| a.files[0].path.cmp(&b.files[0].path));
+    groups
+}
+
+fn same_data(a: &Candidate, b: &Candidate) -> bool {
+    (a.file_size, a.exact) == (b.file_size, b.exact)
+}
+
+fn same_picture(a: &Candidate, b: &Candidate) -> bool {
+    match (a.perceptual, b.perceptual) {
+        _ if same_data(a, b) => true,
+        (Some((dhash_a, phash_a)), Some((dhash_b, phash_b))) => {
+            (dhash_a ^ dhash_b).count_ones() <= DHASH_LIMIT && (phash_a ^ phash_b).count_ones() <= PHASH_LIMIT
+        },
+        _ => false,
+    }
+}
+
+///the other files of the group that are checked to be the same picture as the kept one, and how many are not,
+///files with the same hash are compared byte by byte
+pub fn duplicates_of(group: &DuplicateGroup, keep: usize) -> (Vec<PathBuf>, usize) {
+    let Some(kept) = group.files.get(keep) else { return (Vec::new(), 0) };
+    let mut kept_data = None;
+    let mut same = Vec::new();
+    let mut differing = 0;
+    for (f, other) in group.files.iter().enumerate() {
+        if f == keep {
+            continue;
+        }
+        let alike = if same_data(kept, other) {
+            let kept_data = kept_data.get_or_insert_with(|| MyModel::read_file_data(&kept.path));
+            kept_data.is_some() && *kept_data == MyModel::read_file_data(&other.path)
+        } else {
+            same_picture(kept, other)
+        };
+        if alike {
+            same.push(other.path.clone());
+        } else {
+            differing += 1;
+        }
+    }
+    (same, differing)
+}
+
+///lists the groups, selecting a file shows it next to another from its group
+pub struct DuplicateReview {
+    win: Window,
+    browser: HoldBrowser,
+    groups: Vec<DuplicateGroup>,
+    lines: Vec<Option<(usize, usize)>>, //group and file pr browser line, none for group headers
+}
+
+impl DuplicateReview {
+    pub fn new(groups: Vec<DuplicateGroup>, tx: Sender<Message>) -> Self {
+        let mut win = Window::default()
+            .with_size(WINDOW_W, WINDOW_H)
+            .with_pos(screen_center().0 - WINDOW_W / 2, screen_center().1 - WINDOW_H / 2)
+            .with_label("Duplicates");
+
+        let mut col = Flex::default_fill().column();
+            col.set_margin(10);
+            col.set_spacing(5);
+            let mut browser = HoldBrowser::default();
+            browser.set_column_widths(&[480, 100]);
+            browser.set_callback(move |_| tx.send(Message::ReviewDuplicate));
+
+            let mut row = Flex::default().row();
+                let mut btn_trash = Button::default().with_label("Trash");
+                btn_trash.set_tooltip("Moves the selected file to the trash.");
+                btn_trash.emit(tx, Message::TrashDuplicate);
+                let mut btn_keep = Button::default().with_label("Keep only this");
+                btn_keep.set_tooltip("Moves the other files of the group to the trash.");
+                btn_keep.emit(tx, Message::KeepDuplicate);
+                let mut btn_skip = Button::default().with_label("Skip group");
+                btn_skip.set_tooltip("Removes the group from the list, no files are changed.");
+                btn_skip.emit(tx, Message::SkipDuplicates);
+                Frame::default();
+                let mut btn_close = Button::default().with_label("Close");
+                row.fixed(&btn_trash, 80);
+                row.fixed(&btn_keep, 110);
+                row.fixed(&btn_skip, 90);
+                row.fixed(&btn_close, 80);
+            row.end();
+            col.fixed(&row, 25);
+        col.end();
+        win.end();
+        win.make_resizable(true);
+        btn_close.set_callback({
+            let mut win = win.clone();
+            move |_| win.hide()
+        });
+        win.show();
+
+        let mut review = Self { win, browser, groups, lines: Vec::new() };
+        review.populate();
+        review
+    }
+
+    pub fn is_shown(&self) -> bool {
+        self.win.shown()
+    }
+
+    pub fn group_count(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.groups.iter().map(|group| group.files.len()).sum()
+    }
+
+    ///group and file of the selected line
+    pub fn selected(&self) -> Option<(usize, usize)> {
+        let line = self.browser.value();
+        if line < 1 {
+            return None;
+        }
+        self.lines.get(line as usize - 1).copied().flatten()
+    }
+
+    pub fn file(&self, group: usize, file: usize) -> Option<&Candidate> {
+        self.groups.get(group)?.files.get(file)
+    }
+
+    pub fn group(&self, group: usize) -> Option<&DuplicateGroup> {
+        self.groups.get(group)
+    }
+
+    ///after the file is gone, groups of one are dropped, the line at the same place is selected
+    pub fn remove_file(&mut self, path: &Path) {
+        let line = self.browser.value();
+        for group in &mut self.groups {
+            group.files.retain(|candidate| candidate.path != path);
+        }
+        self.groups.retain(|group| group.files.len() > 1);
+        self.populate();
+        self.select_near(line);
+    }
+
+    pub fn remove_group(&mut self, group: usize) {
+        let line = self.browser.value();
+        if group < self.groups.len() {
+            self.groups.remove(group);
+        }
+        self.populate();
+        self.select_near(line);
+    }
+
+    fn populate(&mut self) {
+        use human_bytes::human_bytes;
+        self.browser.clear();
+        self.lines.clear();
+        for (g, group) in self.groups.iter().enumerate() {
+            let kind = if group.identical { "identical files" } else { "similar images" };
+            self.browser.add(&format!("@b{}: {kind}, {} files", g + 1, group.files.len()));
+            self.lines.push(None);
+            for (f, candidate) in group.files.iter().enumerate() {
+                let size = candidate.dimensions.map(|(w, h)| format!("{w} x {h}")).unwrap_or_else(|| String::from("?"));
+                self.browser.add(&format!("   {}\t{size}\t{}", candidate.path.display(), human_bytes(candidate.file_size as f64)));
+                self.lines.push(Some((g, f)));
+            }
+        }
+    }
+
+    ///the first file line at or after the line
+    fn select_near(&mut self, line: i32) {
+        let start = (line.max(1) as usize - 1).min(self.lines.len());
+        let found = self.lines[start..].iter().position(Option::is_some).map(|pos| start + pos)
+            .or_else(|| self.lines.iter().rposition(Option::is_some));
+        if let Some(index) = found {
+            self.browser.select(index as i32 + 1);
+        }
+    }
+}
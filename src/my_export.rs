@@ -0,0 +1,283 @@
+pub const DEFAULT_QUALITY: u8 = 90;
+const JPEG_MAX_SEGMENT: usize = 65533; //payload of one jpeg marker segment
+const EXIF_HEADER: &[u8] = b"Exif\0\0";
+
+use std::{error::Error, fs, path::Path};
+
+use image::{codecs::{jpeg::JpegEncoder, png::{CompressionType, FilterType as PngFilter, PngEncoder}}, imageops::FilterType, DynamicImage, ImageDecoder, ImageEncoder};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Png,
+    Jpeg,
+    WebP, //lossless at quality 100
+}
+
+impl ExportFormat {
+    ///in the order of the save dialog filters
+    pub const ALL: [ExportFormat; 3] = [ExportFormat::Png, ExportFormat::Jpeg, ExportFormat::WebP];
+
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "png" => Some(ExportFormat::Png),
+            "jpg" | "jpeg" => Some(ExportFormat::Jpeg),
+            "webp" => Some(ExportFormat::WebP),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png => "png",
+            ExportFormat::Jpeg => "jpg",
+            ExportFormat::WebP => "webp",
+        }
+    }
+
+    ///filter lines for the native file chooser
+    pub fn dialog_filter() -> String {
+        String::from("PNG\t*.png\nJPEG\t*.{jpg,jpeg}\nWebP\t*.webp")
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum PngCompression {
+    Fast,
+    Default,
+    Best,
+}
+
+///color profile and exif block as found in the source file
+#[derive(Clone, Default)]
+pub struct Metadata {
+    pub icc: Option<Vec<u8>>,
+    pub exif: Option<Vec<u8>>, //tiff structure without the jpeg exif header
+}
+
+impl Metadata {
+    ///reads what the image crate decoders expose, before the decoder is used up
+    pub fn read(decoder: &mut impl ImageDecoder) -> Self {
+        let icc = decoder.icc_profile().ok().flatten();
+        let exif = decoder.exif_metadata().ok().flatten().map(|exif| {
+            exif.strip_prefix(EXIF_HEADER).map(|tiff| tiff.to_vec()).unwrap_or(exif)
+        });
+        Self { icc, exif }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.icc.is_none() && self.exif.is_none()
+    }
+}
+
+#[derive(Clone)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub quality: u8, //jpeg and webp, 1 to 100
+    pub png_compression: PngCompression,
+    pub crop: Option<(f32, f32, f32, f32)>, //x, y, width and height in normalized source coordinates
+    pub resize: Option<(u32, u32)>, //fit within, keeping the aspect ratio
+    pub keep_metadata: bool,
+}
+
+impl ExportOptions {
+    pub fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            quality: DEFAULT_QUALITY,
+            png_compression: PngCompression::Default,
+            crop: None,
+            resize: None,
+            keep_metadata: false,
+        }
+    }
+}
+
+///rounds a normalized rectangle to whole pixels of an image of the given size, at least one pixel
+pub fn pixel_rect(rect: (f32, f32, f32, f32), size: (f32, f32)) -> (u32, u32, u32, u32) {
+    let (x, y, w, h) = rect;
+    let x0 = (x * size.0).round().clamp(0., size.0 - 1.);
+    let y0 = (y * size.1).round().clamp(0., size.1 - 1.);
+    let x1 = ((x + w) * size.0).round().clamp(x0 + 1., size.0);
+    let y1 = ((y + h) * size.1).round().clamp(y0 + 1., size.1);
+    (x0 as u32, y0 as u32, (x1 - x0) as u32, (y1 - y0) as u32)
+}
+
+///writes the image, cropped and resized as asked, returns the written size
+pub fn export(image: &DynamicImage, metadata: Option<&Metadata>, path: &Path, options: &ExportOptions) -> Result<(u32, u32), Box<dyn Error>> {
+    let mut image = match options.crop {
+        Some(rect) => {
+            let (x, y, w, h) = pixel_rect(rect, (image.width() as f32, image.height() as f32));
+            image.crop_imm(x, y, w, h)
+        },
+        None => image.clone(),
+    };
+    if let Some((w, h)) = options.resize {
+        image = image.resize(w, h, FilterType::Lanczos3);
+    }
+
+    let size = (image.width(), image.height());
+    let metadata = metadata.filter(|_| options.keep_metadata).cloned().unwrap_or_default();
+    let mut data = Vec::new();
+    match options.format {
+        ExportFormat::Png => {
+            //png has no float samples, 16 bits keeps most of the precision
+            let image = match image {
+                DynamicImage::ImageRgb32F(_) => DynamicImage::ImageRgb16(image.to_rgb16()),
+                DynamicImage::ImageRgba32F(_) => DynamicImage::ImageRgba16(image.to_rgba16()),
+                _ => image,
+            };
+            let compression = match options.png_compression {
+                PngCompression::Fast => CompressionType::Fast,
+                PngCompression::Default => CompressionType::Default,
+                PngCompression::Best => CompressionType::Best,
+            };
+            let mut encoder = PngEncoder::new_with_quality(&mut data, compression, PngFilter::Adaptive);
+            if let Some(icc) = &metadata.icc {
+                encoder.set_icc_profile(icc.clone())?;
+            }
+            image.write_with_encoder(encoder)?;
+            if let Some(exif) = &metadata.exif {
+                data = png_with_exif(data, exif);
+            }
+        },
+
+        ExportFormat::Jpeg => {
+            let image = match image {
+                DynamicImage::ImageLuma8(_) => image,
+                _ => DynamicImage::ImageRgb8(image.to_rgb8()), //no alpha in jpeg
+            };
+            let mut encoder = JpegEncoder::new_with_quality(&mut data, options.quality.clamp(1, 100));
+            if let Some(icc) = &metadata.icc {
+                encoder.set_icc_profile(icc.clone())?;
+            }
+            image.write_with_encoder(encoder)?;
+            if let Some(exif) = &metadata.exif {
+                data = jpeg_with_exif(data, exif);
+            }
+        },
+
+        ExportFormat::WebP => {
+            let has_alpha = image.color().has_alpha();
+            let image = if has_alpha {
+                DynamicImage::ImageRgba8(image.to_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.to_rgb8())
+            };
+            let encoder = if has_alpha {
+                webp::Encoder::from_rgba(image.as_bytes(), size.0, size.1)
+            } else {
+                webp::Encoder::from_rgb(image.as_bytes(), size.0, size.1)
+            };
+            let encoded = if options.quality >= 100 {
+                encoder.encode_lossless()
+            } else {
+                encoder.encode(options.quality.max(1) as f32)
+            };
+            data = webp_with_metadata(encoded.to_vec(), &metadata, has_alpha, size);
+        },
+    }
+
+    fs::write(path, data)?;
+    Ok(size)
+}
+
+///adds an exif segment right after the start of image marker
+fn jpeg_with_exif(data: Vec<u8>, exif: &[u8]) -> Vec<u8> {
+    let payload_len = EXIF_HEADER.len() + exif.len();
+    if payload_len > JPEG_MAX_SEGMENT || data.len() < 2 {
+        return data; //too large for one segment, saved without it
+    }
+    let mut res = Vec::with_capacity(data.len() + payload_len + 4);
+    res.extend_from_slice(&data[..2]);
+    res.extend_from_slice(&[0xFF, 0xE1]);
+    res.extend_from_slice(&((payload_len + 2) as u16).to_be_bytes());
+    res.extend_from_slice(EXIF_HEADER);
+    res.extend_from_slice(exif);
+    res.extend_from_slice(&data[2..]);
+    res
+}
+
+///adds an exif chunk after the header chunk, which is always first
+fn png_with_exif(data: Vec<u8>, exif: &[u8]) -> Vec<u8> {
+    let after_ihdr = 8 + 4 + 4 + 13 + 4; //signature, length, type, header data and crc
+    if data.len() < after_ihdr {
+        return data;
+    }
+    let mut res = Vec::with_capacity(data.len() + exif.len() + 12);
+    res.extend_from_slice(&data[..after_ihdr]);
+    res.extend_from_slice(&(exif.len() as u32).to_be_bytes());
+    let start = res.len();
+    res.extend_from_slice(b"eXIf");
+    res.extend_from_slice(exif);
+    let crc = crc32(&res[start..]);
+    res.extend_from_slice(&crc.to_be_bytes());
+    res.extend_from_slice(&data[after_ihdr..]);
+    res
+}
+
+///metadata needs the extended webp layout, with the feature flags in a leading header chunk
+fn webp_with_metadata(data: Vec<u8>, metadata: &Metadata, has_alpha: bool, size: (u32, u32)) -> Vec<u8> {
+    if metadata.is_empty() || data.len() < 12 || &data[..4] != b"RIFF" || &data[8..12] != b"WEBP" {
+        return data;
+    }
+
+    let mut flags = 0u8;
+    let mut image_chunks = Vec::new();
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let fourcc = &data[pos..pos + 4];
+        let len = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]) as usize;
+        let end = (pos + 8 + len).min(data.len());
+        if fourcc == b"VP8X" {
+            flags = data.get(pos + 8).copied().unwrap_or(0);
+        } else {
+            image_chunks.push((&data[pos..pos + 4], &data[pos + 8..end]));
+        }
+        pos = end + (len & 1); //chunks are padded to even sizes
+    }
+
+    if has_alpha { flags |= 0x10; }
+    if metadata.icc.is_some() { flags |= 0x20; }
+    if metadata.exif.is_some() { flags |= 0x08; }
+    let mut header = vec![flags, 0, 0, 0];
+    header.extend_from_slice(&(size.0 - 1).to_le_bytes()[..3]);
+    header.extend_from_slice(&(size.1 - 1).to_le_bytes()[..3]);
+
+    let mut chunks: Vec<(&[u8], &[u8])> = vec![(&b"VP8X"[..], &header[..])];
+    if let Some(icc) = &metadata.icc {
+        chunks.push((&b"ICCP"[..], &icc[..]));
+    }
+    chunks.extend(image_chunks);
+    if let Some(exif) = &metadata.exif {
+        chunks.push((&b"EXIF"[..], &exif[..]));
+    }
+
+    let mut body = Vec::new();
+    for (fourcc, payload) in chunks {
+        body.extend_from_slice(fourcc);
+        body.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        body.extend_from_slice(payload);
+        if payload.len() % 2 == 1 {
+            body.push(0);
+        }
+    }
+    let mut res = Vec::with_capacity(body.len() + 12);
+    res.extend_from_slice(b"RIFF");
+    res.extend_from_slice(&((body.len() + 4) as u32).to_le_bytes());
+    res.extend_from_slice(b"WEBP");
+    res.extend_from_slice(&body);
+    res
+}
+
+///the checksum png chunks end with
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
@@ -0,0 +1,245 @@
+use std::{collections::HashSet, error::Error, fs, io::ErrorKind, path::{Path, PathBuf}};
+
+const RENAMING_SUFFIX: &str = ".renaming"; //names taken over within a batch rename go through a temporary name
+
+///a finished file operation, kept so it can be undone
+pub enum FileOp {
+    Trashed { original: PathBuf },
+    Moved { from: PathBuf, to: PathBuf },
+    Copied { to: PathBuf },
+    Renamed { from: PathBuf, to: PathBuf },
+    Batch(Vec<FileOp>), //undone as one, last first
+}
+
+impl FileOp {
+    ///paths whose listings and cached data are out of date afterwards
+    pub fn affected(&self) -> Vec<PathBuf> {
+        match self {
+            FileOp::Trashed { original } => vec![original.clone()],
+            FileOp::Moved { from, to } | FileOp::Renamed { from, to } => vec![from.clone(), to.clone()],
+            FileOp::Copied { to } => vec![to.clone()],
+            FileOp::Batch(ops) => ops.iter().flat_map(|op| op.affected()).collect(),
+        }
+    }
+
+    ///old and new path of files that were moved or renamed
+    pub fn moves(&self) -> Vec<(PathBuf, PathBuf)> {
+        match self {
+            FileOp::Moved { from, to } | FileOp::Renamed { from, to } => vec![(from.clone(), to.clone())],
+            FileOp::Trashed { .. } | FileOp::Copied { .. } => Vec::new(),
+            FileOp::Batch(ops) => ops.iter().flat_map(|op| op.moves()).collect(),
+        }
+    }
+
+    ///for the statusbar
+    pub fn describe(&self) -> String {
+        match self {
+            FileOp::Trashed { original } => format!("Moved {} to the trash", name(original)),
+            FileOp::Moved { from, to } => format!("Moved {} to {}", name(from), parent(to)),
+            FileOp::Copied { to } => format!("Copied {} to {}", name(to), parent(to)),
+            FileOp::Renamed { from, to } => format!("Renamed {} to {}", name(from), name(to)),
+            FileOp::Batch(ops) => match ops.first() {
+                Some(FileOp::Trashed { .. }) => format!("Moved {} files to the trash", ops.len()),
+                Some(FileOp::Moved { to, .. }) => format!("Moved {} files to {}", ops.len(), parent(to)),
+                Some(FileOp::Renamed { .. }) => {
+                    let renamed = ops.iter().filter(|op| !matches!(op, FileOp::Renamed { to, .. } if name(to).ends_with(RENAMING_SUFFIX))).count();
+                    format!("Renamed {renamed} files")
+                },
+                _ => format!("Changed {} files", ops.len()),
+            },
+        }
+    }
+}
+
+///moves the file or folder to the freedesktop trash
+pub fn trash(path: &Path) -> Result<FileOp, Box<dyn Error>> {
+    check_on_disk(path)?;
+    trash::delete(path)?;
+    Ok(FileOp::Trashed { original: path.to_path_buf() })
+}
+
+pub fn move_to(path: &Path, dir: &Path) -> Result<FileOp, Box<dyn Error>> {
+    check_on_disk(path)?;
+    let to = destination(path, dir)?;
+    move_path(path, &to)?;
+    Ok(FileOp::Moved { from: path.to_path_buf(), to })
+}
+
+///files only, folders are left to a file manager
+pub fn copy_to(path: &Path, dir: &Path) -> Result<FileOp, Box<dyn Error>> {
+    check_on_disk(path)?;
+    if path.is_dir() {
+        return Err("Only files can be copied".into());
+    }
+    let to = destination(path, dir)?;
+    fs::copy(path, &to)?;
+    Ok(FileOp::Copied { to })
+}
+
+///renames within the same folder
+pub fn rename(path: &Path, new_name: &str) -> Result<FileOp, Box<dyn Error>> {
+    check_on_disk(path)?;
+    let new_name = new_name.trim();
+    if new_name.is_empty() || new_name.contains('/') || new_name == "." || new_name == ".." {
+        return Err(format!("\"{new_name}\" is not a valid file name").into());
+    }
+    let to = path.with_file_name(new_name);
+    if to == path {
+        return Err("The name is unchanged".into());
+    }
+    if to.symlink_metadata().is_ok() {
+        return Err(format!("{} already exists", name(&to)).into());
+    }
+    fs::rename(path, &to)?;
+    Ok(FileOp::Renamed { from: path.to_path_buf(), to })
+}
+
+///runs the operation on each path, returns the ones that succeeded as one undoable operation and the errors
+pub fn batch(paths: &[PathBuf], op: impl Fn(&Path) -> Result<FileOp, Box<dyn Error>>) -> (Option<FileOp>, Vec<Box<dyn Error>>) {
+    let mut done = Vec::new();
+    let mut errors = Vec::new();
+    for path in paths {
+        match op(path) {
+            Ok(file_op) => done.push(file_op),
+            Err(e) => errors.push(e),
+        }
+    }
+    let done = if done.is_empty() { None } else { Some(FileOp::Batch(done)) };
+    (done, errors)
+}
+
+///renames each path to its new name in one undoable operation, names can be swapped or shifted within the batch
+pub fn rename_all(renames: &[(PathBuf, String)]) -> (Option<FileOp>, Vec<Box<dyn Error>>) {
+    let sources: HashSet<&Path> = renames.iter().map(|(path, _)| path.as_path()).collect();
+    let mut done = Vec::new();
+    let mut errors = Vec::new();
+
+    //files whose new name is still taken by another file of the batch move aside first
+    let mut second = Vec::new();
+    for (n, (path, new_name)) in renames.iter().enumerate() {
+        let to = path.with_file_name(new_name.trim());
+        let res = if to != *path && sources.contains(to.as_path()) {
+            let aside = format!(".{}.{n}{RENAMING_SUFFIX}", name(path));
+            rename(path, &aside).inspect(|_| second.push((path, path.with_file_name(&aside), new_name)))
+        } else {
+            rename(path, new_name)
+        };
+        match res {
+            Ok(op) => done.push(op),
+            Err(e) => errors.push(e),
+        }
+    }
+    for (original, aside, new_name) in second {
+        match rename(&aside, new_name) {
+            Ok(op) => done.push(op),
+            //back to the old name when it is still free, otherwise moving it aside stays in the undo
+            Err(e) if original.symlink_metadata().is_err() && fs::rename(&aside, original).is_ok() => {
+                done.retain(|op| !matches!(op, FileOp::Renamed { to, .. } if *to == aside));
+                errors.push(e);
+            },
+            Err(e) => errors.push(format!("{e}, {} is left as {}", name(original), name(&aside)).into()),
+        }
+    }
+    let done = if done.is_empty() { None } else { Some(FileOp::Batch(done)) };
+    (done, errors)
+}
+
+///reverses the operation, a copy is moved to the trash rather than deleted, a batch keeps what is left when one fails
+pub fn undo(op: &mut FileOp) -> Result<(), Box<dyn Error>> {
+    match op {
+        FileOp::Trashed { original } => {
+            if original.symlink_metadata().is_ok() {
+                return Err(format!("{} exists again, not restoring it", original.display()).into());
+            }
+            restore_from_trash(original)?;
+        },
+
+        FileOp::Moved { from, to } => {
+            if from.symlink_metadata().is_ok() {
+                return Err(format!("{} exists again, not moving it back", from.display()).into());
+            }
+            move_path(to, from)?;
+        },
+
+        FileOp::Copied { to } => trash::delete(to)?,
+
+        FileOp::Renamed { from, to } => {
+            if from.symlink_metadata().is_ok() {
+                return Err(format!("{} exists again, not renaming it back", from.display()).into());
+            }
+            fs::rename(to, from)?;
+        },
+
+        FileOp::Batch(ops) => {
+            while let Some(last) = ops.last_mut() {
+                undo(last)?;
+                ops.pop();
+            }
+        },
+    }
+    Ok(())
+}
+
+///paths inside archives are virtual and can't be changed
+fn check_on_disk(path: &Path) -> Result<(), Box<dyn Error>> {
+    if path.symlink_metadata().is_ok() {
+        return Ok(());
+    }
+    if path.ancestors().skip(1).any(|ancestor| ancestor.is_file()) {
+        return Err(format!("{} is inside an archive, files in archives can't be changed", name(path)).into());
+    }
+    Err(format!("{} was not found", path.display()).into())
+}
+
+///same name in the folder, never overwriting
+fn destination(path: &Path, dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()).into());
+    }
+    let to = dir.join(path.file_name().ok_or("The path has no file name")?);
+    if to == path {
+        return Err("The file is already in that folder".into());
+    }
+    if to.symlink_metadata().is_ok() {
+        return Err(format!("{} already exists in {}", name(path), dir.display()).into());
+    }
+    Ok(to)
+}
+
+///renames, or copies and removes when the folders are on different filesystems
+fn move_path(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::CrossesDevices && from.is_file() => {
+            fs::copy(from, to)?;
+            fs::remove_file(from)?;
+            Ok(())
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+///the most recently trashed item from that path, the trash crate can only list the trash on windows and freedesktop systems
+#[cfg(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android"))))]
+fn restore_from_trash(original: &Path) -> Result<(), Box<dyn Error>> {
+    let item = trash::os_limited::list()?
+        .into_iter()
+        .filter(|item| item.original_path() == original)
+        .max_by_key(|item| item.time_deleted)
+        .ok_or_else(|| format!("{} is no longer in the trash", name(original)))?;
+    trash::os_limited::restore_all([item])?;
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", all(unix, not(target_os = "macos"), not(target_os = "ios"), not(target_os = "android")))))]
+fn restore_from_trash(original: &Path) -> Result<(), Box<dyn Error>> {
+    Err(format!("Restoring from the trash is not supported on this system, {} is still in it", name(original)).into())
+}
+
+fn name(path: &Path) -> String {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy().to_string()
+}
+
+fn parent(path: &Path) -> String {
+    path.parent().map(|parent| parent.display().to_string()).unwrap_or_default()
+}
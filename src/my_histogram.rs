@@ -0,0 +1,95 @@
+pub const BINS: usize = 256;
+const LUMA_WEIGHTS: [f32; 3] = [0.2126, 0.7152, 0.0722]; //rec. 709
+
+use image::{DynamicImage, GenericImageView};
+
+#[derive(Clone, Copy)]
+pub struct ChannelStats {
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+}
+
+///red, green, blue and luminance, bins cover 0 to 1 and hdr values above 1 land in the last bin
+#[derive(Clone)]
+pub struct Histogram {
+    pub bins: [Vec<u32>; 4],
+    pub stats: [ChannelStats; 4],
+    pub peak: u32, //highest bin, for scaling the drawing
+    pub region: bool, //made from the visible part only
+}
+
+impl Histogram {
+    ///region is x, y, width and height in normalized source coordinates
+    pub fn compute(image: &DynamicImage, region: Option<(f32, f32, f32, f32)>) -> Self {
+        let (w, h) = image.dimensions();
+        let (x0, y0, x1, y1) = match region {
+            Some((x, y, rw, rh)) => (
+                ((x * w as f32) as u32).min(w),
+                ((y * h as f32) as u32).min(h),
+                (((x + rw) * w as f32).ceil() as u32).min(w),
+                (((y + rh) * h as f32).ceil() as u32).min(h),
+            ),
+            None => (0, 0, w, h),
+        };
+
+        let mut bins = [vec![0u32; BINS], vec![0u32; BINS], vec![0u32; BINS], vec![0u32; BINS]];
+        let mut min = [f32::MAX; 4];
+        let mut max = [f32::MIN; 4];
+        let mut sum = [0f64; 4];
+
+        for y in y0..y1 {
+            for x in x0..x1 {
+                let [r, g, b] = sample(image, x, y);
+                let luma = r * LUMA_WEIGHTS[0] + g * LUMA_WEIGHTS[1] + b * LUMA_WEIGHTS[2];
+                for (channel, value) in [r, g, b, luma].into_iter().enumerate() {
+                    let bin = (value.clamp(0., 1.) * (BINS - 1) as f32).round() as usize;
+                    bins[channel][bin] += 1;
+                    min[channel] = min[channel].min(value);
+                    max[channel] = max[channel].max(value);
+                    sum[channel] += value as f64;
+                }
+            }
+        }
+
+        let count = ((x1 - x0) as f64 * (y1 - y0) as f64).max(1.);
+        let stats = std::array::from_fn(|channel| ChannelStats {
+            min: if min[channel] == f32::MAX { 0. } else { min[channel] },
+            max: if max[channel] == f32::MIN { 0. } else { max[channel] },
+            mean: (sum[channel] / count) as f32,
+        });
+        let peak = bins.iter().flat_map(|channel| channel.iter()).copied().max().unwrap_or(0);
+
+        Self { bins, stats, peak, region: region.is_some() }
+    }
+
+    ///min, max and mean pr channel for the statusbar
+    pub fn summary(&self) -> String {
+        let names = ["R", "G", "B", "L"];
+        let parts: Vec<String> = names.iter().zip(self.stats.iter())
+            .map(|(name, s)| format!("{name} {:.2}-{:.2} mean {:.2}", s.min, s.max, s.mean))
+            .collect();
+        let scope = if self.region { "Visible" } else { "Image" };
+        format!("{scope}: {}", parts.join("  "))
+    }
+}
+
+///the pixel as floats, keeping values above 1 in hdr images
+fn sample(image: &DynamicImage, x: u32, y: u32) -> [f32; 3] {
+    match image {
+        DynamicImage::ImageRgb32F(buf) => buf.get_pixel(x, y).0,
+        DynamicImage::ImageRgba32F(buf) => {
+            let [r, g, b, _] = buf.get_pixel(x, y).0;
+            [r, g, b]
+        },
+        DynamicImage::ImageRgb16(buf) => buf.get_pixel(x, y).0.map(|v| v as f32 / u16::MAX as f32),
+        DynamicImage::ImageRgba16(buf) => {
+            let [r, g, b, _] = buf.get_pixel(x, y).0;
+            [r, g, b].map(|v| v as f32 / u16::MAX as f32)
+        },
+        _ => {
+            let [r, g, b, _] = image.get_pixel(x, y).0;
+            [r, g, b].map(|v| v as f32 / u8::MAX as f32)
+        },
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+const MAX_VISITS: usize = 100;
+
+///a listing as it was left
+pub struct Visit {
+    pub path: PathBuf,
+    pub selected: Option<PathBuf>, //browser entry
+    pub image: Option<PathBuf>, //being viewed
+}
+
+///back and forward through visited listings like a web browser
+#[derive(Default)]
+pub struct History {
+    back: Vec<Visit>,
+    forward: Vec<Visit>,
+}
+
+impl History {
+    ///going to a new listing drops the steps forward
+    pub fn visit(&mut self, left: Visit) {
+        if self.back.len() >= MAX_VISITS {
+            self.back.remove(0);
+        }
+        self.back.push(left);
+        self.forward.clear();
+    }
+
+    ///where a step would go, to check it can still be listed
+    pub fn peek(&self, forward: bool) -> Option<&Visit> {
+        if forward { self.forward.last() } else { self.back.last() }
+    }
+
+    ///the current listing is kept to step back to
+    pub fn step(&mut self, forward: bool, current: Visit) -> Option<Visit> {
+        let (from, to) = if forward { (&mut self.forward, &mut self.back) } else { (&mut self.back, &mut self.forward) };
+        let visit = from.pop()?;
+        to.push(current);
+        Some(visit)
+    }
+}
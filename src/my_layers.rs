@@ -0,0 +1,125 @@
+const PSD_MAGIC: &[u8; 4] = b"8BPS";
+const EXR_MAGIC: [u8; 4] = [0x76, 0x2F, 0x31, 0x01];
+
+use std::{collections::HashMap, error::Error, io::Cursor};
+
+use image::{DynamicImage, Rgb32FImage, Rgba32FImage, RgbaImage};
+
+pub fn is_psd(data: &[u8]) -> bool {
+    data.starts_with(PSD_MAGIC)
+}
+
+pub fn is_exr(data: &[u8]) -> bool {
+    data.starts_with(&EXR_MAGIC)
+}
+
+///the flattened composite first, then each top level layer or group
+pub fn decode_psd(data: &[u8]) -> Result<Vec<(String, DynamicImage)>, Box<dyn Error>> {
+    use psd::Psd;
+
+    let psd = Psd::from_bytes(data)?;
+    let (w, h) = (psd.width(), psd.height());
+    let to_image = |rgba: Vec<u8>| RgbaImage::from_raw(w, h, rgba).map(DynamicImage::ImageRgba8).ok_or("Bad image size");
+
+    let mut res = vec![(String::from("Composite"), to_image(psd.rgba())?)];
+
+    let parents: HashMap<u32, Option<u32>> = psd.groups().iter().map(|group| (group.id(), group.parent_id())).collect();
+    //walks up from a group to see if it sits inside another one
+    let inside = |mut parent: Option<u32>, group_id: u32| {
+        while let Some(id) = parent {
+            if id == group_id {
+                return true;
+            }
+            parent = parents.get(&id).copied().flatten();
+        }
+        false
+    };
+
+    for group in psd.groups().iter().filter(|group| group.parent_id().is_none()) {
+        let rgba = psd.flatten_layers_rgba(&|(_, layer)| inside(layer.parent_id(), group.id()))?;
+        res.push((group.name().to_string(), to_image(rgba)?));
+    }
+    for layer in psd.layers().iter().filter(|layer| layer.parent_id().is_none()) {
+        res.push((layer.name().to_string(), to_image(layer.rgba())?));
+    }
+    Ok(res)
+}
+
+///every layer with red, green and blue channels in color, followed by all channels one by one
+pub fn decode_exr(data: &[u8]) -> Result<Vec<(String, DynamicImage)>, Box<dyn Error>> {
+    use exr::prelude::*;
+
+    let image = read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .all_channels()
+        .all_layers()
+        .all_attributes()
+        .from_buffered(Cursor::new(data))?;
+
+    let mut colors = Vec::new();
+    let mut channels = Vec::new();
+
+    for layer in &image.layer_data {
+        let part_name = layer.attributes.layer_name.as_ref().map(|name| name.to_string());
+        let (w, h) = (layer.size.width() as u32, layer.size.height() as u32);
+        let samples = |channel: &AnyChannel<FlatSamples>| -> Vec<f32> {
+            (0..channel.sample_data.len()).map(|n| channel.sample_data.value_by_flat_index(n).to_f32()).collect()
+        };
+
+        //channel names like "diffuse.R" put several layers in one part, "R" belongs to the unnamed layer
+        let mut by_layer: Vec<(String, Vec<&AnyChannel<FlatSamples>>)> = Vec::new();
+        for channel in &layer.channel_data.list {
+            let name = channel.name.to_string();
+            let prefix = name.rsplit_once('.').map(|(prefix, _)| prefix.to_string()).unwrap_or_default();
+            match by_layer.iter_mut().find(|(known, _)| *known == prefix) {
+                Some((_, list)) => list.push(channel),
+                None => by_layer.push((prefix, vec![channel])),
+            }
+        }
+
+        for (prefix, list) in by_layer {
+            let full_name = match (&part_name, prefix.is_empty()) {
+                (Some(part), true) => part.clone(),
+                (Some(part), false) => format!("{part}.{prefix}"),
+                (None, true) => String::from("RGB"),
+                (None, false) => prefix.clone(),
+            };
+            let find = |suffix: &str| list.iter().find(|channel| {
+                let name = channel.name.to_string();
+                name.rsplit('.').next().is_some_and(|last| last.eq_ignore_ascii_case(suffix))
+            });
+
+            if let (Some(r), Some(g), Some(b)) = (find("R"), find("G"), find("B")) {
+                let (r, g, b) = (samples(r), samples(g), samples(b));
+                let image = match find("A") {
+                    Some(a) => {
+                        let a = samples(a);
+                        let rgba = (0..r.len()).flat_map(|n| [r[n], g[n], b[n], a[n]]).collect();
+                        DynamicImage::ImageRgba32F(Rgba32FImage::from_raw(w, h, rgba).ok_or("Bad image size")?)
+                    },
+                    None => {
+                        let rgb = (0..r.len()).flat_map(|n| [r[n], g[n], b[n]]).collect();
+                        DynamicImage::ImageRgb32F(Rgb32FImage::from_raw(w, h, rgb).ok_or("Bad image size")?)
+                    },
+                };
+                colors.push((full_name, image));
+            }
+
+            for channel in list {
+                let gray = samples(channel).into_iter().flat_map(|v| [v, v, v]).collect();
+                let name = match &part_name {
+                    Some(part) => format!("{part}.{}", channel.name),
+                    None => channel.name.to_string(),
+                };
+                channels.push((name, DynamicImage::ImageRgb32F(Rgb32FImage::from_raw(w, h, gray).ok_or("Bad image size")?)));
+            }
+        }
+    }
+
+    colors.extend(channels);
+    if colors.is_empty() {
+        return Err("No channels in image".into());
+    }
+    Ok(colors)
+}
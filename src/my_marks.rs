@@ -0,0 +1,114 @@
+use crate::{EntryType, Listing};
+
+///pick state while culling
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Flag {
+    #[default]
+    None,
+    Keep,
+    Reject,
+}
+
+///stored in xmp sidecars, see my_xmp
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Mark {
+    pub flag: Flag,
+    pub stars: u8, //0 to 5
+    pub label: Option<String>, //color label like Red or Green, other programs may use any text
+    pub tags: Vec<String>,
+}
+
+///what a key press does to the mark
+#[derive(Clone, PartialEq)]
+pub enum MarkChange {
+    Keep, //pressing again removes it
+    Reject,
+    Stars(u8), //the same rating again removes it
+    Label(&'static str), //the same label again removes it
+    Tags(Vec<String>),
+    Clear, //keep, reject and stars, labels and tags stay
+}
+
+///which images the browser lists
+#[derive(Clone, PartialEq)]
+pub enum MarkFilter {
+    All,
+    Kept,
+    Rejected,
+    NotRejected,
+    Unmarked,
+    Starred,
+    Labeled,
+    Search(String), //part of a tag or label, ignoring case
+}
+
+impl Mark {
+    pub fn apply(&mut self, change: MarkChange) {
+        match change {
+            MarkChange::Keep => self.flag = if self.flag == Flag::Keep { Flag::None } else { Flag::Keep },
+            MarkChange::Reject => self.flag = if self.flag == Flag::Reject { Flag::None } else { Flag::Reject },
+            MarkChange::Stars(stars) => self.stars = if self.stars == stars { 0 } else { stars.min(5) },
+            MarkChange::Label(label) => {
+                self.label = if self.label.as_deref() == Some(label) { None } else { Some(label.to_string()) };
+            },
+            MarkChange::Tags(tags) => self.tags = tags,
+            MarkChange::Clear => {
+                self.flag = Flag::None;
+                self.stars = 0;
+            },
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Mark::default()
+    }
+
+    ///short text for the statusbar and the browser
+    pub fn label(&self) -> String {
+        let flag = match self.flag {
+            Flag::None => "",
+            Flag::Keep => "✓",
+            Flag::Reject => "✗",
+        };
+        let label = self.label.as_ref().map(|label| format!(" {label}")).unwrap_or_default();
+        format!("{flag}{}{label}", "★".repeat(self.stars as usize))
+    }
+
+    ///tags separated by commas
+    pub fn tags_text(&self) -> String {
+        self.tags.join(", ")
+    }
+
+    ///from text separated by commas, without duplicates
+    pub fn parse_tags(text: &str) -> Vec<String> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in text.split(',').map(str::trim).filter(|tag| !tag.is_empty()) {
+            if !tags.iter().any(|known| known == tag) {
+                tags.push(tag.to_string());
+            }
+        }
+        tags
+    }
+}
+
+impl MarkFilter {
+    ///folders and archives are always listed so the filter doesn't get in the way of browsing
+    pub fn shows(&self, listing: &Listing, mark: &Mark) -> bool {
+        if listing.entry_type != EntryType::Image {
+            return true;
+        }
+        match self {
+            MarkFilter::All => true,
+            MarkFilter::Kept => mark.flag == Flag::Keep,
+            MarkFilter::Rejected => mark.flag == Flag::Reject,
+            MarkFilter::NotRejected => mark.flag != Flag::Reject,
+            MarkFilter::Unmarked => mark.flag == Flag::None && mark.stars == 0,
+            MarkFilter::Starred => mark.stars > 0,
+            MarkFilter::Labeled => mark.label.is_some(),
+            MarkFilter::Search(text) => {
+                let text = text.to_lowercase();
+                mark.label.iter().chain(mark.tags.iter()).any(|tag| tag.to_lowercase().contains(&text))
+            },
+        }
+    }
+}
@@ -1,6 +1,18 @@
-use fltk::{enums::{FrameType, Shortcut}, menu, prelude::{MenuExt, WidgetExt}};
+use fltk::{enums::{FrameType, Key, Shortcut}, menu, prelude::{MenuExt, WidgetExt}};
+
+use crate::{my_marks::{MarkChange, MarkFilter}, CompareMode, Message, SpreadMode, ZoomModifier};
+
+///radio items of the browser filter, a tag search has none
+pub const MARK_FILTERS: [(&str, MarkFilter); 7] = [
+    ("&Marks/&Show/&All\t", MarkFilter::All),
+    ("&Marks/&Show/&Kept\t", MarkFilter::Kept),
+    ("&Marks/&Show/&Rejected\t", MarkFilter::Rejected),
+    ("&Marks/&Show/&Not rejected\t", MarkFilter::NotRejected),
+    ("&Marks/&Show/&Unmarked\t", MarkFilter::Unmarked),
+    ("&Marks/&Show/&Starred\t", MarkFilter::Starred),
+    ("&Marks/&Show/&Labeled\t", MarkFilter::Labeled),
+];
 
-use crate::Message;
 pub struct MyMenu {
     pub menu: menu::SysMenuBar, //pub for flex
 }
@@ -18,6 +30,70 @@ impl MyMenu {
             Message::ToggleFullscreen,
         );
 
+        menu.add_emit(
+            "&File/Save &as...\t",
+            Shortcut::Ctrl | 's',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::SaveAs,
+        );
+
+        menu.add_emit(
+            "&File/&Export selection...\t",
+            Shortcut::Ctrl | 'e',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::ExportSelection,
+        );
+
+        menu.add_emit(
+            "&File/&Rename...\t",
+            Shortcut::None | Key::F2,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::RenameFile,
+        );
+
+        menu.add_emit(
+            "&File/&Batch rename...\t",
+            Shortcut::Ctrl | Key::F2,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::BatchRename,
+        );
+
+        menu.add_emit(
+            "&File/&Move to...\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::MoveFile,
+        );
+
+        menu.add_emit(
+            "&File/&Copy to...\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::CopyFile,
+        );
+
+        menu.add_emit(
+            "&File/Find &duplicates...\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::FindDuplicates,
+        );
+
+        menu.add_emit(
+            "&File/Move to &trash...\t",
+            Shortcut::Ctrl | Key::Delete,
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::TrashFile,
+        );
+
         menu.add_emit(
             "&File/&Quit\t",
             Shortcut::Ctrl | 'q',
@@ -26,6 +102,38 @@ impl MyMenu {
             Message::Quit,
         );
 
+        menu.add_emit(
+            "&Edit/&Undo file change\t",
+            Shortcut::Ctrl | 'z',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::UndoFileOp,
+        );
+
+        menu.add_emit(
+            "&Edit/&Copy image and path\t",
+            Shortcut::Ctrl | 'c',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::CopyImage,
+        );
+
+        menu.add_emit(
+            "&Go/&Back\t",
+            Shortcut::Alt | Key::Left,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::GoBack,
+        );
+
+        menu.add_emit(
+            "&Go/&Forward\t",
+            Shortcut::Alt | Key::Right,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::GoForward,
+        );
+
         menu.add_emit(
             "&View/Keep image &aspect ratio\t",
             Shortcut::Ctrl | 'a',
@@ -50,6 +158,272 @@ impl MyMenu {
             Message::Zoom1to1,
         );
 
+        menu.add_emit(
+            "&View/Wheel &zoom modifier/&Ctrl\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetZoomModifier(ZoomModifier::Ctrl),
+        );
+
+        menu.add_emit(
+            "&View/Wheel &zoom modifier/&Shift\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetZoomModifier(ZoomModifier::Shift),
+        );
+
+        menu.add_emit(
+            "&View/Wheel &zoom modifier/&Alt\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetZoomModifier(ZoomModifier::Alt),
+        );
+
+        if let Some(mut item) = menu.find_item("&View/Wheel &zoom modifier/&Ctrl\t") {
+            item.set();
+        }
+
+        menu.add_emit(
+            "&View/Rotate &left\t",
+            Shortcut::None | '[',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::RotateLeft,
+        );
+
+        menu.add_emit(
+            "&View/Rotate &right\t",
+            Shortcut::None | ']',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::RotateRight,
+        );
+
+        menu.add_emit(
+            "&View/Flip &horizontal\t",
+            Shortcut::None | 'h',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::FlipHorizontal,
+        );
+
+        menu.add_emit(
+            "&View/Flip &vertical\t",
+            Shortcut::None | 'v',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::FlipVertical,
+        );
+
+        menu.add_emit(
+            "&View/Next la&yer\t",
+            Shortcut::None | 'l',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::NextLayer,
+        );
+
+        menu.add_emit(
+            "&View/Previous lay&er\t",
+            Shortcut::Shift | 'l',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::PrevLayer,
+        );
+
+        menu.add_emit(
+            "&View/&Pages/&Single page\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetSpreadMode(SpreadMode::Single),
+        );
+
+        menu.add_emit(
+            "&View/&Pages/Two pages, &left to right\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetSpreadMode(SpreadMode::LeftToRight),
+        );
+
+        menu.add_emit(
+            "&View/&Pages/Two pages, &right to left\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetSpreadMode(SpreadMode::RightToLeft),
+        );
+
+        if let Some(mut item) = menu.find_item("&View/&Pages/&Single page\t") {
+            item.set();
+        }
+
+        menu.add_emit(
+            "&View/C&ompare/&Off\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetCompareMode(CompareMode::Off),
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/Side &by side\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetCompareMode(CompareMode::SideBySide),
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/&Difference\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetCompareMode(CompareMode::Difference),
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/&Wipe\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio,
+            *tx,
+            Message::SetCompareMode(CompareMode::Wipe),
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/&Flicker\t",
+            Shortcut::None,
+            menu::MenuFlag::Radio | menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::SetCompareMode(CompareMode::Flicker),
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/Switch flicker image\t",
+            Shortcut::None | 'f',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::ToggleFlicker,
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/Flicker &automatically\t",
+            Shortcut::Shift | 'f',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::ToggleFlickerTimer,
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/Compare with &selected\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::CompareWithSelected,
+        );
+
+        menu.add_emit(
+            "&View/C&ompare/Compare with &next\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::CompareWithNext,
+        );
+
+        if let Some(mut item) = menu.find_item("&View/C&ompare/&Off\t") {
+            item.set();
+        }
+
+        menu.add_emit(
+            "&View/Sli&deshow/&Start or stop\t",
+            Shortcut::None | Key::F5,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::ToggleSlideshow,
+        );
+
+        menu.add_emit(
+            "&View/Sli&deshow/Set &interval...\t",
+            Shortcut::None,
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::SlideshowSetInterval,
+        );
+
+        menu.add_emit(
+            "&View/Sli&deshow/S&huffle\t",
+            Shortcut::None,
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::SlideshowShuffle,
+        );
+
+        menu.add_emit(
+            "&View/Sli&deshow/&Loop\t",
+            Shortcut::None,
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::SlideshowLoop,
+        );
+
+        menu.add_emit(
+            "&View/Sli&deshow/&Pause on input\t",
+            Shortcut::None,
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::SlideshowPauseOnInput,
+        );
+
+        menu.add_emit(
+            "&View/Sli&deshow/&Crossfade\t",
+            Shortcut::None,
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::SlideshowCrossfade,
+        );
+
+        for checked in ["&View/Sli&deshow/&Loop\t", "&View/Sli&deshow/&Pause on input\t"] {
+            if let Some(mut item) = menu.find_item(checked) {
+                item.set();
+            }
+        }
+
+        menu.add_emit(
+            "&View/Histo&gram\t",
+            Shortcut::None | 'g',
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::ToggleHistogram,
+        );
+
+        menu.add_emit(
+            "&View/Histogram of visible &region\t",
+            Shortcut::None,
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::ToggleHistogramRegion,
+        );
+
+        menu.add_emit(
+            "&View/&Minimap\t",
+            Shortcut::None | 'm',
+            menu::MenuFlag::Toggle,
+            *tx,
+            Message::ToggleMinimap,
+        );
+
+        menu.add_emit(
+            "&View/&Comic info...\t",
+            Shortcut::None,
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::ShowComicInfo,
+        );
+
         menu.add_emit(
             "&View/&Fullscreen\t", //do some alt + enter, dbl_click?
             Shortcut::Ctrl | 'f',
@@ -82,6 +456,97 @@ impl MyMenu {
             Message::GoDark,
         );
 
+        menu.add_emit(
+            "&Marks/&Keep\t",
+            Shortcut::None | 'k',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::SetMark(MarkChange::Keep),
+        );
+
+        menu.add_emit(
+            "&Marks/&Reject\t",
+            Shortcut::None | 'x',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::SetMark(MarkChange::Reject),
+        );
+
+        for stars in 1..=5u8 {
+            let label = if stars == 1 { String::from("&Marks/&1 star\t") } else { format!("&Marks/&{stars} stars\t") };
+            menu.add_emit(
+                &label,
+                Shortcut::None | char::from(b'0' + stars),
+                menu::MenuFlag::Normal,
+                *tx,
+                Message::SetMark(MarkChange::Stars(stars)),
+            );
+        }
+
+        menu.add_emit(
+            "&Marks/&Clear\t",
+            Shortcut::None | '0',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::SetMark(MarkChange::Clear),
+        );
+
+        for (label, key) in [("Red", Some('6')), ("Yellow", Some('7')), ("Green", Some('8')), ("Blue", Some('9')), ("Purple", None)] {
+            menu.add_emit(
+                &format!("&Marks/&Label/&{label}\t"),
+                key.map(|key| Shortcut::None | key).unwrap_or(Shortcut::None),
+                menu::MenuFlag::Normal,
+                *tx,
+                Message::SetMark(MarkChange::Label(label)),
+            );
+        }
+
+        menu.add_emit(
+            "&Marks/Edit &tags...\t",
+            Shortcut::None | 't',
+            menu::MenuFlag::MenuDivider,
+            *tx,
+            Message::EditTags,
+        );
+
+        for (label, filter) in MARK_FILTERS {
+            menu.add_emit(
+                label,
+                Shortcut::None,
+                menu::MenuFlag::Radio,
+                *tx,
+                Message::SetMarkFilter(filter),
+            );
+        }
+
+        menu.add_emit(
+            "&Marks/&Show/Search &tags...\t",
+            Shortcut::Ctrl | 't',
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::SearchTags,
+        );
+
+        if let Some(mut item) = menu.find_item("&Marks/&Show/&All\t") {
+            item.set();
+        }
+
+        menu.add_emit(
+            "&Marks/Move rejects to &trash\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::TrashRejects,
+        );
+
+        menu.add_emit(
+            "&Marks/&Move rejects to...\t",
+            Shortcut::None,
+            menu::MenuFlag::Normal,
+            *tx,
+            Message::MoveRejects,
+        );
+
         menu.add_emit(
             "&Help/&About\t",
             Shortcut::None,
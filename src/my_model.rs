@@ -1,31 +1,64 @@
 const DEFAULT_BROWSER_SELECTION: i32 = 1;
-const IMAGE_EXTENSIONS: [&str; 18] = ["bmp", "dds", "ff", "gif", "hdr", "ico", "jpg", "jpeg", "exr", "png", "pnm", "qoi", "tga", "tif", "tiff", "webp", "heic", "heif"];
-const ARCHIVE_EXTENSIONS: [&str; 12] = ["iso", "zip", "7z", "cab", "rar", "xar", "lzh", "lha", "gz", "bz2", "zst", "jar"]; //lzma??
+const IMAGE_EXTENSIONS: [&str; 31] = ["bmp", "dds", "ff", "gif", "hdr", "ico", "jpg", "jpeg", "exr", "png", "pnm", "qoi", "tga", "tif", "tiff", "webp", "heic", "heif", "avif", "jxl", "svg", "svgz", "cr2", "cr3", "nef", "arw", "dng", "orf", "rw2", "raf", "psd"];
+const HEIF_BRANDS: [&[u8; 4]; 10] = [b"heic", b"heix", b"hevc", b"hevx", b"heim", b"heis", b"mif1", b"msf1", b"avif", b"avis"];
+const JXL_CODESTREAM: [u8; 2] = [0xFF, 0x0A];
+const JXL_CONTAINER: [u8; 12] = [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A];
+const HEIF_SEQUENCE_BRANDS: [&[u8; 4]; 3] = [b"avis", b"msf1", b"heis"];
+const ANIMATION_FRAME_LIMIT: usize = 2000;
+const ANIMATION_BYTES_LIMIT: usize = 1 << 30; //decoded frames of one animation, later frames are left out
+const SNIFF_LENGTH: u64 = 1024; //room for an xml prolog in front of <svg
+const EMBEDDED_XMP_READ_LIMIT: u64 = 1 << 20; //xmp is near the start of jpegs, pngs and tiffs, listing doesn't read whole files
+const ARCHIVE_EXTENSIONS: [&str; 16] = ["iso", "zip", "7z", "cab", "rar", "xar", "lzh", "lha", "gz", "bz2", "zst", "jar", "cbz", "cbr", "cb7", "cbt"]; //lzma??
+const ZIP_EXTENSIONS: [&str; 2] = ["zip", "cbz"]; //read with the zip crate, can be listed inside other archives
 
-use std::{collections::{HashMap, HashSet}, error::Error, fs::{self, File}, io::{self, BufReader, Cursor, ErrorKind, Read}, path::{Component, Path, PathBuf}};
+use std::{collections::{HashMap, HashSet}, error::Error, ffi::{CStr, OsStr}, fs::{self, File}, io::{self, BufReader, Cursor, ErrorKind, Read}, path::{Component, Path, PathBuf}, ptr, slice};
 
 use archive_reader::Archive;
 use archive_reader::error::Result;
 
 use fltk::app::Sender;
 use image::{DynamicImage, ImageReader};
-use speedy2d::image::ImageHandle;
 
+use jxl_oxide::JxlImage;
 use libheif_rs::HeifContext;
 use libheif_rs::LibHeif;
 
 
-use crate::{EntryType, Listing, Message};
+use crate::{my_comic::{ComicInfo, COMIC_INFO_NAME}, my_display::{Animation, MipChain, MipImages}, my_export::Metadata, my_layers, my_marks::{Flag, Mark, MarkChange, MarkFilter}, my_raw::{self, RAW_EXTENSIONS}, my_xmp, EntryType, Listing, Message};
+
+///formats that are not decoded by the image crate
+#[derive(PartialEq)]
+enum ImageKind {
+    Heif, //also avif
+    Jxl,
+    Svg, //also svgz
+    Raw, //camera raw, shown by its embedded preview
+    Psd,
+    Exr, //all layers and channels, single layer files are still read by the image crate
+}
 
 pub struct MyModel {
     tx: Sender<Message>,
     cwd: PathBuf,
     listings: HashMap<PathBuf, Vec<Listing>>, //cached directory and archive listings
     data_cache: HashMap<PathBuf,Vec<u8>>, //archives and compressed images
-    pub image_cache: HashMap<PathBuf, DynamicImage>, //decompressed images
-    pub texture_cache: HashMap<PathBuf, ImageHandle>, //images on gpu
+    pub image_cache: HashMap<PathBuf, MipImages>, //decompressed images with their mip levels
+    pub texture_cache: HashMap<PathBuf, MipChain>, //images on gpu
+    pub animation_cache: HashMap<PathBuf, Animation>, //all frames of animated images on gpu
+    pub layers: HashMap<PathBuf, Vec<(String, MipImages)>>, //named layers of the displayed psd or exr image, counted as cached images
+    pub layer_index: HashMap<PathBuf, usize>, //the layer shown for each layered image
+    pub metadata: HashMap<PathBuf, Metadata>, //icc profile and exif, kept for saving
     pub trying_to_load: HashSet<PathBuf>, //trying to load these
+    vector_sources: HashMap<PathBuf, Vec<u8>>, //svg data, kept for rasterizing at higher zoom levels
+    vector_scales: HashMap<PathBuf, f32>, //largest scale rasterized or being rasterized
+    comic_info: HashMap<PathBuf, ComicInfo>, //parsed ComicInfo.xml pr archive
     pub(crate) data_in_cache_size: usize,
+    pub marks: HashMap<PathBuf, Mark>, //keep, reject, stars, labels and tags, saved in xmp sidecars
+    marks_read: HashSet<PathBuf>, //images with a sidecar or changed here, embedded xmp doesn't override these
+    archive_dbs: HashSet<PathBuf>, //archives whose sidecar database is read
+    embedded_searched: HashSet<PathBuf>, //listed images searched for embedded xmp
+    mark_filter: MarkFilter,
+    shown: Option<Vec<Listing>>, //the filtered current listing, none when showing all
 }
 
 impl MyModel {
@@ -34,8 +67,8 @@ impl MyModel {
         let cwd = PathBuf::from(start_path);
         let listings: HashMap<PathBuf, Vec<Listing>> = HashMap::new();
         let data_cache: HashMap<PathBuf,Vec<u8>> = HashMap::new();
-        let image_cache: HashMap<PathBuf, DynamicImage> = HashMap::new();
-        let texture_cache: HashMap<PathBuf, ImageHandle> = HashMap::new();
+        let image_cache: HashMap<PathBuf, MipImages> = HashMap::new();
+        let texture_cache: HashMap<PathBuf, MipChain> = HashMap::new();
         let trying_to_load: HashSet<PathBuf> = HashSet::new();
 
         Self {
@@ -45,11 +78,28 @@ impl MyModel {
             data_cache,
             image_cache,
             texture_cache,
+            animation_cache: HashMap::new(),
+            layers: HashMap::new(),
+            layer_index: HashMap::new(),
+            metadata: HashMap::new(),
             trying_to_load,
+            vector_sources: HashMap::new(),
+            vector_scales: HashMap::new(),
+            comic_info: HashMap::new(),
             data_in_cache_size: 0,
+            marks: HashMap::new(),
+            marks_read: HashSet::new(),
+            archive_dbs: HashSet::new(),
+            embedded_searched: HashSet::new(),
+            mark_filter: MarkFilter::All,
+            shown: None,
           }
     }
 
+    pub fn get_cwd(&self) -> PathBuf {
+        self.cwd.clone()
+    }
+
     pub fn goto_parent(&mut self) -> Result<(), Box<dyn Error>> {
         if let Some(parent) = self.cwd.parent() {
             let new_path = PathBuf::from(parent);
@@ -59,8 +109,113 @@ impl MyModel {
         Ok(())
     }
 
+    ///lists the folder or archive at the path, also inside archives, an image is shown in the listing of its folder
+    pub fn goto_path(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if !path.is_dir() && self.find_entry(path).is_some_and(|entry| entry.entry_type == EntryType::Image) {
+            let parent = path.parent().ok_or("The image has no folder")?;
+            let new_listing = self.get_listing(parent)?;
+            self.tx.send(Message::ShowListing(new_listing, parent.to_path_buf()));
+            self.tx.send(Message::WantToDisplay(path.to_path_buf()));
+        } else {
+            let new_listing = self.listing_at(path)?;
+            self.tx.send(Message::ShowListing(new_listing, path.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    ///like get_listing, but a path that isn't a folder or archive, on disk or in an archive, is an error
+    pub fn listing_at(&mut self, path: &Path) -> Result<Vec<Listing>, Box<dyn Error>> {
+        let entry_type = if path.is_dir() { Some(EntryType::Dir) } else { self.find_entry(path).map(|entry| entry.entry_type) };
+        match entry_type {
+            Some(EntryType::Dir | EntryType::Archive) => {
+                if !path.exists() {
+                    self.listings.entry(path.to_path_buf()).or_default(); //folders in archives without files aren't listed
+                }
+                self.get_listing(path)
+            },
+
+            Some(_) => Err(format!("{} is not a folder or archive", path.display()).into()),
+
+            None => Err(format!("{} was not found", path.display()).into()),
+        }
+    }
+
+    ///a typed path made absolute, . and .. are resolved by the text because paths in archives are virtual
+    pub fn typed_path(&self, text: &str) -> PathBuf {
+        let text = text.trim();
+        let path = match (text.strip_prefix('~'), std::env::var_os("HOME")) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => PathBuf::from(home).join(rest.trim_start_matches('/')),
+            _ => PathBuf::from(text),
+        };
+        let path = if path.is_relative() { self.cwd.join(path) } else { path };
+
+        let mut res = PathBuf::new();
+        for component in path.components() {
+            match component {
+                Component::CurDir => {},
+                Component::ParentDir => { res.pop(); },
+                other => res.push(other),
+            }
+        }
+        res
+    }
+
+    ///completes the last part of the typed text with folders and archives, returns the new text and the names that fit
+    pub fn complete_path(&mut self, text: &str) -> (String, Vec<String>) {
+        let (dir_text, prefix) = match text.rsplit_once('/') {
+            Some((dir_text, prefix)) => (Some(format!("{dir_text}/")), prefix),
+            None => (None, text),
+        };
+        let dir = self.typed_path(dir_text.as_deref().unwrap_or("."));
+
+        let mut names: Vec<String> = self.listing_of(&dir).map(|listing| listing.iter()
+            .filter(|entry| matches!(entry.entry_type, EntryType::Dir | EntryType::Archive) || entry.file_path.is_dir())
+            .filter_map(|entry| entry.file_path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .filter(|name| name.starts_with(prefix))
+            .collect())
+            .unwrap_or_default();
+        names.sort();
+
+        let completed = match names.as_slice() {
+            [] => prefix.to_string(),
+            [only] => format!("{only}/"),
+            [first, rest @ ..] => {
+                let mut common = first.clone();
+                for name in rest {
+                    while !name.starts_with(&common) {
+                        common.pop();
+                    }
+                }
+                common
+            },
+        };
+        (format!("{}{completed}", dir_text.unwrap_or_default()), names)
+    }
+
+    ///listing of a folder or archive without making it the current directory, archives on the way are listed too
+    fn listing_of(&mut self, dir: &Path) -> Option<&Vec<Listing>> {
+        if !self.listings.contains_key(dir) {
+            //files on disk are only listed when they are archives
+            let listable = dir.is_dir() || (dir.is_file() && has_archive_extension(dir))
+                || self.find_entry(dir).is_some_and(|entry| matches!(entry.entry_type, EntryType::Dir | EntryType::Archive));
+            if !listable {
+                return None;
+            }
+            let cwd = self.cwd.clone();
+            let res = self.list_path(dir);
+            self.cwd = cwd;
+            res.ok()?;
+        }
+        self.listings.get(dir)
+    }
+
+    ///the listed entry of a path on disk or inside archives
+    fn find_entry(&mut self, path: &Path) -> Option<Listing> {
+        self.listing_of(path.parent()?)?.iter().find(|entry| entry.file_path == path).cloned()
+    }
+
     pub fn open_item(&mut self, browser_item_nr: i32) -> Result<(), Box<dyn Error>> {
-        if let Some(current_listing) = self.listings.get(&self.cwd) {
+        if let Some(current_listing) = self.current_listing() {
             if current_listing.len() >= browser_item_nr as usize {
                 let selected = current_listing[browser_item_nr as usize-1].clone(); //browser is 1 based, vector 0 based
                 match selected.entry_type {
@@ -78,7 +233,12 @@ impl MyModel {
 
                     EntryType::Link => (Ok(())), //lookup what link points too somehow
                     
-                    EntryType::File => {Ok(())}, //don't do much if not img or archive, alt/ctrl click to force type?
+                    EntryType::File => { //files without an image extension are only looked into when opened
+                        if MyModel::has_image_signature(&selected.file_path, true) {
+                            self.tx.send(Message::WantToDisplay(selected.file_path));
+                        }
+                        Ok(())
+                    },
                     
                     EntryType::Image => {
                         self.tx.send(Message::WantToDisplay(selected.file_path));
@@ -93,11 +253,91 @@ impl MyModel {
         }
     }
 
+    ///path of a browser item of any type
+    pub fn get_item_path(&self, browser_item_nr: i32) -> Option<PathBuf> {
+        let listing = self.current_listing()?.get((browser_item_nr as usize).checked_sub(1)?)?;
+        Some(listing.file_path.clone())
+    }
+
+    ///forgets listings and cached data of a path changed on disk, and of everything below it, returns the freed gpu and image bytes
+    pub fn invalidate(&mut self, path: &Path) -> (usize, usize) {
+        if let Some(parent) = path.parent() {
+            self.listings.remove(parent);
+        }
+        self.listings.retain(|listed, _| !listed.starts_with(path));
+        self.archive_dbs.retain(|archive| !archive.starts_with(path));
+
+        let mut data_freed = 0;
+        self.data_cache.retain(|pb, data| {
+            let keep = !pb.starts_with(path);
+            if !keep { data_freed += data.len(); }
+            keep
+        });
+        self.data_in_cache_size -= data_freed;
+
+        let mut image_freed = 0;
+        self.image_cache.retain(|pb, images| {
+            let keep = !pb.starts_with(path);
+            if !keep { image_freed += images.data_size(); }
+            keep
+        });
+
+        //the first frame of an animation is in both caches, count it once
+        let mut gpu_freed = 0;
+        let animations = &self.animation_cache;
+        self.texture_cache.retain(|pb, chain| {
+            let keep = !pb.starts_with(path);
+            if !keep && !animations.contains_key(pb) { gpu_freed += chain.data_size(); }
+            keep
+        });
+        self.animation_cache.retain(|pb, animation| {
+            let keep = !pb.starts_with(path);
+            if !keep { gpu_freed += animation.data_size(); }
+            keep
+        });
+
+        self.layers.retain(|pb, layers| {
+            let keep = !pb.starts_with(path);
+            if !keep { image_freed += layers.iter().map(|(_, images)| images.data_size()).sum::<usize>(); }
+            keep
+        });
+        self.layer_index.retain(|pb, _| !pb.starts_with(path));
+        self.metadata.retain(|pb, _| !pb.starts_with(path));
+        self.vector_sources.retain(|pb, _| !pb.starts_with(path));
+        self.vector_scales.retain(|pb, _| !pb.starts_with(path));
+        self.comic_info.retain(|pb, _| !pb.starts_with(path));
+        (gpu_freed, image_freed)
+    }
+
+    ///path of a browser item if it is an image
+    pub fn get_image_path(&self, browser_item_nr: i32) -> Option<PathBuf> {
+        let listing = self.current_listing()?.get((browser_item_nr as usize).checked_sub(1)?)?;
+        if listing.entry_type == EntryType::Image {
+            Some(listing.file_path.clone())
+        } else {
+            None
+        }
+    }
+
     //if not image in texture_cache, decoded_image_cache, data_cache load from disk or archive
     pub fn load_image_data(&mut self, image_pb: PathBuf) {
         if self.texture_cache.contains_key(&image_pb) {return;}
         if self.image_cache.contains_key(&image_pb) {return;}
+        self.fetch_image_data(image_pb);
+    }
 
+    ///decodes an image again when its pixels are needed but only its texture is kept
+    pub fn reload_image_data(&mut self, image_pb: PathBuf) {
+        if self.image_cache.contains_key(&image_pb) {return;}
+        if let Some(&scale) = self.vector_scales.get(&image_pb) { //a new raster at the same scale
+            self.vector_scales.insert(image_pb.clone(), 0.);
+            self.rasterize_vector(image_pb, scale);
+            return;
+        }
+        self.fetch_image_data(image_pb);
+    }
+
+    fn fetch_image_data(&mut self, image_pb: PathBuf) {
         if self.data_cache.contains_key(&image_pb) {
             self.tx.send(Message::ImageLoaded(image_pb));
             return;
@@ -143,7 +383,15 @@ impl MyModel {
             self.data_in_cache_size -= image_data.len();
             let tx = self.tx;
 
+            if MyModel::image_kind_from_extension(&image_pb) == Some(ImageKind::Svg) {
+                self.vector_sources.insert(image_pb.clone(), image_data.clone());
+                self.vector_scales.insert(image_pb.clone(), 1.);
+            }
+
             std::thread::spawn(move || { //use more controlled threading, lookup builder mutex saturate
+                if let Some(mark) = my_xmp::embedded_mark(&image_data) {
+                    tx.send(Message::EmbeddedMark(mark, image_pb.clone()));
+                }
                 let res = MyModel::try_decode_image(tx, image_data, image_pb.to_path_buf());
                 if res.is_err() {
                     tx.send(Message::Info(String::from(format!("Problem decoding image, {:?}", image_pb))));
@@ -153,46 +401,377 @@ impl MyModel {
     }
 
     fn try_decode_image(tx: Sender<Message>, image_data: Vec<u8>, image_pb: PathBuf) -> Result<bool, Box<dyn Error>>{
-        if let Some(ex) = image_pb.extension() {            
-            if ex.eq_ignore_ascii_case("heic") || ex.eq_ignore_ascii_case("heif") {
-                let lib_heif = LibHeif::new();
-                let ctx = HeifContext::read_from_bytes(&image_data)?;
-                let handle = ctx.primary_image_handle()?;
-                let has_alpha = handle.has_alpha_channel();
-                let color_space = if has_alpha {
-                    libheif_rs::ColorSpace::Rgb(libheif_rs::RgbChroma::Rgba)
-                } else {
-                    libheif_rs::ColorSpace::Rgb(libheif_rs::RgbChroma::Rgb)
-                };
-            
-                if let Ok(img) = lib_heif.decode(&handle, color_space, None) {
-                    if let Some(inter) = img.planes().interleaved {
-                        if has_alpha {
-                            if let Some(buf) = image::ImageBuffer::from_vec(inter.width, inter.height, inter.data.to_vec()) {
-                                let image = DynamicImage::ImageRgba8(buf);
-                                tx.send(Message::ImageDecoded(image, image_pb));
-                                return Ok(true);
-                            }
-                        } else { //no alpha
-                            if let Some(buf) = image::ImageBuffer::from_vec(inter.width, inter.height, inter.data.to_vec()) {
-                                let image = DynamicImage::ImageRgb8(buf);
-                                tx.send(Message::ImageDecoded(image, image_pb));
-                                return Ok(true);
-                            }
+        //trust the file signature over the extension
+        match MyModel::sniff_image_kind(&image_data).or_else(|| MyModel::image_kind_from_extension(&image_pb)) {
+            Some(ImageKind::Heif) => {
+                if image_data.get(8..12).is_some_and(|brand| HEIF_SEQUENCE_BRANDS.iter().any(|b| b.as_slice() == brand)) {
+                    let (frames, cut) = MyModel::decode_heif_sequence(&image_data)?;
+                    if frames.len() > 1 {
+                        if cut {
+                            tx.send(Message::Info(format!("Long animation, only the first {} frames are shown", frames.len())));
                         }
+                        let frames = frames.into_iter().map(|(frame, delay)| (MipImages::build(frame), delay)).collect();
+                        tx.send(Message::AnimationDecoded(frames, image_pb));
+                        return Ok(true);
                     }
                 }
-            } else { //not heic
+                let image = MyModel::decode_heif(&image_data)?;
+                tx.send(Message::ImageDecoded(MipImages::build(image), image_pb));
+                Ok(true)
+            },
+
+            Some(ImageKind::Jxl) => {
+                let (mut frames, cut) = MyModel::decode_jxl(&image_data, ANIMATION_FRAME_LIMIT)?;
+                if cut {
+                    tx.send(Message::Info(format!("Long animation, only the first {} frames are shown", frames.len())));
+                }
+                if frames.len() > 1 {
+                    let frames = frames.into_iter().map(|(frame, delay)| (MipImages::build(frame), delay)).collect();
+                    tx.send(Message::AnimationDecoded(frames, image_pb));
+                    Ok(true)
+                } else if let Some((image, _)) = frames.pop() {
+                    tx.send(Message::ImageDecoded(MipImages::build(image), image_pb));
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            },
+
+            Some(ImageKind::Svg) => {
+                let (image, logical_size) = MyModel::rasterize_svg(&image_data, 1.)?;
+                tx.send(Message::VectorDecoded(MipImages::build(image), image_pb, logical_size, 1.));
+                Ok(true)
+            },
+
+            Some(ImageKind::Raw) => {
+                let image = my_raw::decode_raw(&image_data)?;
+                tx.send(Message::ImageDecoded(MipImages::build(image), image_pb));
+                Ok(true)
+            },
+
+            Some(ImageKind::Psd) => {
+                let layers = my_layers::decode_psd(&image_data)?;
+                tx.send(Message::LayersDecoded(MyModel::build_layers(layers), image_pb));
+                Ok(true)
+            },
+
+            Some(ImageKind::Exr) => {
+                let layers = my_layers::decode_exr(&image_data)?;
+                tx.send(Message::LayersDecoded(MyModel::build_layers(layers), image_pb));
+                Ok(true)
+            },
+
+            None => {
                 let buf_read = Cursor::new(image_data);
                 let maybe_image = ImageReader::new(buf_read);
                 if let Ok(img) = maybe_image.with_guessed_format() {
-                    let image = img.decode()?;
-                    tx.send(Message::ImageDecoded(image, image_pb));
+                    let mut decoder = img.into_decoder()?;
+                    let metadata = Metadata::read(&mut decoder);
+                    let image = DynamicImage::from_decoder(decoder)?;
+                    if !metadata.is_empty() {
+                        tx.send(Message::MetadataRead(metadata, image_pb.clone()));
+                    }
+                    tx.send(Message::ImageDecoded(MipImages::build(image), image_pb));
                     return Ok(true);
                 }
+                Ok(false) //this should err out somehow
+            },
+        }
+    }
+
+    ///each layer gets its mip levels here, switching layers only uploads
+    fn build_layers(layers: Vec<(String, DynamicImage)>) -> Vec<(String, MipImages)> {
+        layers.into_iter().map(|(name, image)| (name, MipImages::build(image))).collect()
+    }
+
+    fn sniff_image_kind(data: &[u8]) -> Option<ImageKind> {
+        if data.starts_with(&JXL_CODESTREAM) || data.starts_with(&JXL_CONTAINER) {
+            return Some(ImageKind::Jxl);
+        }
+        if MyModel::is_svg(data) {
+            return Some(ImageKind::Svg);
+        }
+        //iso bmff, the major brand follows the ftyp box type
+        if data.len() >= 12 && &data[4..8] == b"ftyp" && HEIF_BRANDS.iter().any(|brand| &data[8..12] == *brand) {
+            return Some(ImageKind::Heif);
+        }
+        if my_raw::is_raw_data(data) {
+            return Some(ImageKind::Raw);
+        }
+        if my_layers::is_psd(data) {
+            return Some(ImageKind::Psd);
+        }
+        if my_layers::is_exr(data) {
+            return Some(ImageKind::Exr);
+        }
+        None
+    }
+
+    ///the root element, after a byte order mark, whitespace, the xml declaration, comments and a doctype
+    fn is_svg(data: &[u8]) -> bool {
+        let mut rest = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        loop {
+            rest = rest.trim_ascii_start();
+            let end: &[u8] = if rest.starts_with(b"<?") {
+                b"?>"
+            } else if rest.starts_with(b"<!--") {
+                b"-->"
+            } else if rest.starts_with(b"<!") {
+                b">"
+            } else {
+                return rest.starts_with(b"<svg");
+            };
+            match rest.windows(end.len()).position(|window| window == end) {
+                Some(pos) => rest = &rest[pos + end.len()..],
+                None => return false,
+            }
+        }
+    }
+
+    fn image_kind_from_extension(image_pb: &Path) -> Option<ImageKind> {
+        let ex = image_pb.extension()?;
+        if ex.eq_ignore_ascii_case("heic") || ex.eq_ignore_ascii_case("heif") || ex.eq_ignore_ascii_case("avif") {
+            Some(ImageKind::Heif)
+        } else if ex.eq_ignore_ascii_case("jxl") {
+            Some(ImageKind::Jxl)
+        } else if ex.eq_ignore_ascii_case("svg") || ex.eq_ignore_ascii_case("svgz") {
+            Some(ImageKind::Svg)
+        } else if RAW_EXTENSIONS.iter().any(|rawx| ex.eq_ignore_ascii_case(rawx)) {
+            Some(ImageKind::Raw) //most raw formats look like tiff so the extension decides
+        } else {
+            None
+        }
+    }
+
+    ///libheif decodes both heic and avif, deeper than 8 bit images are kept at 16 bit
+    fn decode_heif(image_data: &[u8]) -> Result<DynamicImage, Box<dyn Error>> {
+        use libheif_rs::{ColorSpace, RgbChroma};
+
+        let lib_heif = LibHeif::new();
+        let ctx = HeifContext::read_from_bytes(image_data)?;
+        let handle = ctx.primary_image_handle()?; //the still, sequences are read by decode_heif_sequence
+        let has_alpha = handle.has_alpha_channel();
+        let bits = handle.luma_bits_per_pixel();
+        let high_depth = bits > 8;
+        let chroma = match (high_depth, has_alpha) {
+            (false, false) => RgbChroma::Rgb,
+            (false, true) => RgbChroma::Rgba,
+            (true, false) => RgbChroma::HdrRgbLe,
+            (true, true) => RgbChroma::HdrRgbaLe,
+        };
+
+        let img = lib_heif.decode(&handle, ColorSpace::Rgb(chroma), None)?;
+        let inter = img.planes().interleaved.ok_or("Image has no interleaved plane")?;
+        let channels = if has_alpha { 4 } else { 3 };
+        let bytes_per_sample = if high_depth { 2 } else { 1 };
+
+        //rows can be padded, copy only the pixels
+        let row_len = inter.width as usize * channels * bytes_per_sample;
+        let mut data = Vec::with_capacity(row_len * inter.height as usize);
+        for row in inter.data.chunks(inter.stride).take(inter.height as usize) {
+            data.extend_from_slice(&row[..row_len]);
+        }
+
+        let image = if high_depth {
+            let shift = 16 - bits as u32; //scale 10 and 12 bit samples to the full 16 bit range
+            let samples: Vec<u16> = data.chunks_exact(2).map(|b| u16::from_le_bytes([b[0], b[1]]) << shift).collect();
+            if has_alpha {
+                DynamicImage::ImageRgba16(image::ImageBuffer::from_raw(inter.width, inter.height, samples).ok_or("Bad image size")?)
+            } else {
+                DynamicImage::ImageRgb16(image::ImageBuffer::from_raw(inter.width, inter.height, samples).ok_or("Bad image size")?)
+            }
+        } else if has_alpha {
+            DynamicImage::ImageRgba8(image::ImageBuffer::from_raw(inter.width, inter.height, data).ok_or("Bad image size")?)
+        } else {
+            DynamicImage::ImageRgb8(image::ImageBuffer::from_raw(inter.width, inter.height, data).ok_or("Bad image size")?)
+        };
+        Ok(image)
+    }
+
+    ///every frame of the first track of an animated avif or heif with how long it is shown in seconds,
+    ///libheif-rs has no tracks so libheif is called directly, also says if frames were left out
+    fn decode_heif_sequence(image_data: &[u8]) -> Result<(Vec<(DynamicImage, f64)>, bool), Box<dyn Error>> {
+        use libheif_sys as lh;
+
+        struct Context(*mut lh::heif_context);
+        impl Drop for Context {
+            fn drop(&mut self) {
+                unsafe { lh::heif_context_free(self.0) }
+            }
+        }
+        struct Track(*mut lh::heif_track);
+        impl Drop for Track {
+            fn drop(&mut self) {
+                unsafe { lh::heif_track_release(self.0) }
+            }
+        }
+        struct Frame(*mut lh::heif_image);
+        impl Drop for Frame {
+            fn drop(&mut self) {
+                unsafe { lh::heif_image_release(self.0) }
             }
         }
-        Ok(false) //this should err out somehow
+        let check = |error: lh::heif_error| -> Result<(), Box<dyn Error>> {
+            if error.code == lh::heif_error_code_heif_error_Ok {
+                return Ok(());
+            }
+            let message = unsafe { CStr::from_ptr(error.message) }.to_string_lossy().into_owned();
+            Err(message.into())
+        };
+
+        let _lib_heif = LibHeif::new(); //keeps libheif initialized
+        let context = Context(unsafe { lh::heif_context_alloc() });
+        //the context doesn't copy the data and is freed before the data goes away
+        check(unsafe { lh::heif_context_read_from_memory_without_copy(context.0, image_data.as_ptr().cast(), image_data.len(), ptr::null()) })?;
+        if unsafe { lh::heif_context_has_sequence(context.0) } == 0 {
+            return Ok((Vec::new(), false));
+        }
+        let track = Track(unsafe { lh::heif_context_get_track(context.0, 0) }); //0 is the first visual track
+        if track.0.is_null() {
+            return Ok((Vec::new(), false));
+        }
+        let timescale = unsafe { lh::heif_track_get_timescale(track.0) }.max(1) as f64;
+
+        let mut frames = Vec::new();
+        let mut bytes = 0;
+        loop {
+            let mut img = ptr::null_mut();
+            let error = unsafe {
+                lh::heif_track_decode_next_image(track.0, &mut img, lh::heif_colorspace_heif_colorspace_RGB, lh::heif_chroma_heif_chroma_interleaved_RRGGBBAA_LE, ptr::null())
+            };
+            if error.code == lh::heif_error_code_heif_error_End_of_sequence {
+                return Ok((frames, false));
+            }
+            check(error)?;
+            let img = Frame(img);
+
+            let channel = lh::heif_channel_heif_channel_interleaved;
+            let (width, height) = unsafe { (lh::heif_image_get_width(img.0, channel), lh::heif_image_get_height(img.0, channel)) };
+            let bits = unsafe { lh::heif_image_get_bits_per_pixel_range(img.0, channel) }.clamp(1, 16) as u32;
+            let mut stride = 0;
+            let plane = unsafe { lh::heif_image_get_plane_readonly2(img.0, channel, &mut stride) };
+            if plane.is_null() || width <= 0 || height <= 0 {
+                return Err("Image has no interleaved plane".into());
+            }
+            let (width, height) = (width as u32, height as u32);
+            let row_len = width as usize * 8;
+            let data = unsafe { slice::from_raw_parts(plane, stride * (height as usize - 1) + row_len) };
+
+            //samples are 16 bit whatever the depth, scale them to the full range and keep 8 bit frames at 8 bit
+            let samples = data.chunks(stride).take(height as usize)
+                .flat_map(|row| row[..row_len].chunks_exact(2))
+                .map(|b| u16::from_le_bytes([b[0], b[1]]) << (16 - bits));
+            let frame = if bits > 8 {
+                DynamicImage::ImageRgba16(image::ImageBuffer::from_raw(width, height, samples.collect()).ok_or("Bad image size")?)
+            } else {
+                DynamicImage::ImageRgba8(image::ImageBuffer::from_raw(width, height, samples.map(|v| (v >> 8) as u8).collect()).ok_or("Bad image size")?)
+            };
+
+            bytes += frame.as_bytes().len();
+            if !frames.is_empty() && (frames.len() >= ANIMATION_FRAME_LIMIT || bytes > ANIMATION_BYTES_LIMIT) {
+                return Ok((frames, true));
+            }
+            let duration = unsafe { lh::heif_image_get_duration(img.0) } as f64 / timescale;
+            frames.push((frame, duration));
+        }
+    }
+
+    ///returns up to max_frames frames with how long each is shown in seconds, still images have one frame,
+    ///also says if frames were left out for the frame or memory limit
+    fn decode_jxl(image_data: &[u8], max_frames: usize) -> Result<(Vec<(DynamicImage, f64)>, bool), Box<dyn Error>> {
+        let image = JxlImage::builder().read(Cursor::new(image_data))?;
+        let seconds_per_tick = image.image_header().metadata.animation.as_ref()
+            .map(|animation| animation.tps_denominator as f64 / animation.tps_numerator as f64)
+            .unwrap_or(0.);
+
+        let mut frames = Vec::new();
+        let mut bytes = 0;
+        for keyframe in 0..image.num_loaded_keyframes() {
+            if !frames.is_empty() && (frames.len() >= max_frames || bytes > ANIMATION_BYTES_LIMIT) {
+                return Ok((frames, true));
+            }
+            let render = image.render_frame(keyframe)?;
+            let mut stream = render.stream();
+            let (w, h, channels) = (stream.width(), stream.height(), stream.channels() as usize);
+            let mut buf = vec![0f32; w as usize * h as usize * channels];
+            stream.write_to_buffer(&mut buf);
+
+            //samples are floats, keeping high bit depth and alpha
+            let frame = match channels {
+                1 => {
+                    let rgb = buf.iter().flat_map(|&v| [v, v, v]).collect();
+                    DynamicImage::ImageRgb32F(image::Rgb32FImage::from_raw(w, h, rgb).ok_or("Bad image size")?)
+                },
+                2 => {
+                    let rgba = buf.chunks_exact(2).flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]]).collect();
+                    DynamicImage::ImageRgba32F(image::Rgba32FImage::from_raw(w, h, rgba).ok_or("Bad image size")?)
+                },
+                3 => DynamicImage::ImageRgb32F(image::Rgb32FImage::from_raw(w, h, buf).ok_or("Bad image size")?),
+                _ => {
+                    let rgba = buf.chunks_exact(channels).flat_map(|px| [px[0], px[1], px[2], px[3]]).collect();
+                    DynamicImage::ImageRgba32F(image::Rgba32FImage::from_raw(w, h, rgba).ok_or("Bad image size")?)
+                },
+            };
+            bytes += frame.as_bytes().len();
+            frames.push((frame, render.duration() as f64 * seconds_per_tick));
+        }
+        Ok((frames, false))
+    }
+
+    ///rasterizes again on a thread if the scale is larger than what is already rasterized
+    pub fn rasterize_vector(&mut self, image_pb: PathBuf, scale: f32) {
+        if let Some(data) = self.vector_sources.get(&image_pb) {
+            if self.vector_scales.get(&image_pb).is_some_and(|&done| done >= scale) {
+                return;
+            }
+            self.vector_scales.insert(image_pb.clone(), scale);
+
+            let data = data.clone();
+            let tx = self.tx;
+            std::thread::spawn(move || {
+                match MyModel::rasterize_svg(&data, scale) {
+                    Ok((image, logical_size)) => tx.send(Message::VectorDecoded(MipImages::build(image), image_pb, logical_size, scale)),
+                    Err(e) => tx.send(Message::Info(format!("Problem rasterizing image, {e}"))),
+                }
+            });
+        }
+    }
+
+    ///returns the image and the size of the svg before scaling, svgz is unpacked by usvg
+    fn rasterize_svg(data: &[u8], scale: f32) -> Result<(DynamicImage, (f32, f32)), Box<dyn Error>> {
+        use resvg::{tiny_skia, usvg};
+
+        let tree = usvg::Tree::from_data(data, &usvg::Options::default())?;
+        let size = tree.size();
+        let w = (size.width() * scale).ceil().max(1.) as u32;
+        let h = (size.height() * scale).ceil().max(1.) as u32;
+        let mut pixmap = tiny_skia::Pixmap::new(w, h).ok_or("Bad svg size")?;
+        resvg::render(&tree, tiny_skia::Transform::from_scale(scale, scale), &mut pixmap.as_mut());
+
+        //tiny-skia keeps premultiplied alpha
+        let mut pixels = pixmap.take();
+        for px in pixels.chunks_exact_mut(4) {
+            let a = px[3] as u32;
+            if a > 0 && a < 255 {
+                for c in &mut px[..3] {
+                    *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+                }
+            }
+        }
+
+        let image = DynamicImage::ImageRgba8(image::RgbaImage::from_raw(w, h, pixels).ok_or("Bad svg size")?);
+        Ok((image, (size.width(), size.height())))
+    }
+
+    ///for files without a known image extension, the short magic numbers the image crate knows, like P1 of pnm, also match text files
+    fn has_image_signature(path: &Path, with_short_magics: bool) -> bool {
+        let mut head = Vec::new();
+        if let Ok(f) = File::open(path) {
+            if f.take(SNIFF_LENGTH).read_to_end(&mut head).is_ok() {
+                return MyModel::sniff_image_kind(&head).is_some() || (with_short_magics && image::guess_format(&head).is_ok());
+            }
+        }
+        false
     }
 
     fn locate_resource(&mut self, path: &Path) -> Option<(PathBuf, Option<PathBuf>)> {
@@ -231,7 +810,147 @@ impl MyModel {
         None
     }
 
+    ///listing of the path, filtered by mark, the path becomes the current directory
     pub fn get_listing(&mut self, path: &Path) -> Result<Vec<Listing>, Box<dyn Error>> {
+        let listing = self.list_path(path)?;
+        Ok(self.filter_listing(listing))
+    }
+
+    pub fn get_mark_filter(&self) -> MarkFilter {
+        self.mark_filter.clone()
+    }
+
+    pub fn set_mark_filter(&mut self, filter: MarkFilter) -> Vec<Listing> {
+        self.mark_filter = filter;
+        let listing = self.listings.get(&self.cwd).cloned().unwrap_or_default();
+        self.filter_listing(listing)
+    }
+
+    ///what the browser shows, kept until the next listing so newly marked images can still be stepped through
+    fn filter_listing(&mut self, listing: Vec<Listing>) -> Vec<Listing> {
+        if self.mark_filter == MarkFilter::All {
+            self.shown = None;
+            return listing;
+        }
+        let shown: Vec<Listing> = listing.into_iter()
+            .filter(|entry| self.mark_filter.shows(entry, &self.get_mark(&entry.file_path)))
+            .collect();
+        self.shown = Some(shown.clone());
+        shown
+    }
+
+    fn current_listing(&self) -> Option<&Vec<Listing>> {
+        self.shown.as_ref().or_else(|| self.listings.get(&self.cwd))
+    }
+
+    pub fn get_mark(&self, path: &Path) -> Mark {
+        self.marks.get(path).cloned().unwrap_or_default()
+    }
+
+    ///changes the mark and saves it, it is kept in memory when saving fails
+    pub fn change_mark(&mut self, path: &Path, change: MarkChange) -> Result<Mark, Box<dyn Error>> {
+        let mut mark = self.get_mark(path);
+        mark.apply(change);
+        if mark.is_empty() {
+            self.marks.remove(path);
+        } else {
+            self.marks.insert(path.to_path_buf(), mark.clone());
+        }
+        self.marks_read.insert(path.to_path_buf());
+        self.save_mark(path)?;
+        Ok(mark)
+    }
+
+    ///a rating embedded in the image counts until a sidecar or a change says otherwise, returns whether it was used
+    pub fn set_embedded_mark(&mut self, path: &Path, mark: Mark) -> bool {
+        if !self.marks_read.insert(path.to_path_buf()) {
+            return false;
+        }
+        self.marks.insert(path.to_path_buf(), mark);
+        true
+    }
+
+    ///the mark and its sidecar follow a moved or renamed file
+    pub fn move_mark(&mut self, from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+        if self.marks_read.remove(from) {
+            self.marks_read.insert(to.to_path_buf());
+        }
+        if let Some(mark) = self.marks.remove(from) {
+            self.marks.insert(to.to_path_buf(), mark);
+        }
+        my_xmp::move_sidecar(from, to)
+    }
+
+    ///the sidecar next to the image, or the database of the archive it is in
+    fn save_mark(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        match path.ancestors().skip(1).find(|ancestor| ancestor.is_file()) {
+            Some(archive) => {
+                let entries: Vec<(PathBuf, &Mark)> = self.marks.iter()
+                    .filter_map(|(pb, mark)| Some((pb.strip_prefix(archive).ok()?.to_path_buf(), mark)))
+                    .collect();
+                my_xmp::write_archive_db(archive, &entries)
+            },
+            None => my_xmp::write_sidecar(path, &self.get_mark(path)),
+        }
+    }
+
+    ///marks of the listed images from their sidecars, or from the database of the archive they are in
+    fn read_sidecars(&mut self, listed: &Path) {
+        if listed.is_dir() {
+            let images: Vec<PathBuf> = self.listings.get(listed).map(|listing| listing.iter()
+                .filter(|entry| entry.entry_type == EntryType::Image && !self.marks_read.contains(&entry.file_path))
+                .map(|entry| entry.file_path.clone())
+                .collect()).unwrap_or_default();
+            let mut unmarked = Vec::new();
+            for image in images {
+                if let Some(mark) = my_xmp::read_sidecar(&image) {
+                    self.marks_read.insert(image.clone());
+                    if !mark.is_empty() {
+                        self.marks.insert(image, mark);
+                    }
+                } else if self.embedded_searched.insert(image.clone()) {
+                    unmarked.push(image);
+                }
+            }
+
+            //so filtering and searching see marks of images not viewed yet
+            if !unmarked.is_empty() {
+                let tx = self.tx;
+                std::thread::spawn(move || {
+                    let marks: Vec<(PathBuf, Mark)> = unmarked.into_iter()
+                        .filter_map(|image| {
+                            let mut data = Vec::new();
+                            File::open(&image).and_then(|file| file.take(EMBEDDED_XMP_READ_LIMIT).read_to_end(&mut data)).ok()?;
+                            Some((image, my_xmp::embedded_mark(&data)?))
+                        })
+                        .collect();
+                    if !marks.is_empty() {
+                        tx.send(Message::EmbeddedMarks(marks));
+                    }
+                });
+            }
+        } else if let Some(archive) = listed.ancestors().find(|ancestor| ancestor.is_file()) {
+            if self.archive_dbs.insert(archive.to_path_buf()) {
+                for (inner, mark) in my_xmp::read_archive_db(archive) {
+                    let image = archive.join(inner);
+                    if self.marks_read.insert(image.clone()) && !mark.is_empty() {
+                        self.marks.insert(image, mark);
+                    }
+                }
+            }
+        }
+    }
+
+    ///rejected images in the current directory or archive, whatever the filter
+    pub fn get_rejected(&self) -> Vec<PathBuf> {
+        self.listings.get(&self.cwd).map(|listing| listing.iter()
+            .filter(|entry| entry.entry_type == EntryType::Image && self.get_mark(&entry.file_path).flag == Flag::Reject)
+            .map(|entry| entry.file_path.clone())
+            .collect()).unwrap_or_default()
+    }
+
+    ///the listing as it is on disk or in the archive
+    fn list_path(&mut self, path: &Path) -> Result<Vec<Listing>, Box<dyn Error>> {
         if self.listings.contains_key(path) {
             println!("using cached listing");
             self.cwd = path.to_path_buf();
@@ -245,20 +964,27 @@ impl MyModel {
             Ok(true) => {
                 if path.is_file() {
                     let list = MyModel::get_filelist(path)?;
+                    let comic_info_name = list.iter().find(|name| name.eq_ignore_ascii_case(COMIC_INFO_NAME)).cloned();
                     self.add_filelist_to_directory(list, path.to_path_buf());
+                    self.listings.entry(path.to_path_buf()).or_default(); //an empty archive
+                    if let Some(name) = comic_info_name {
+                        self.read_comic_info(path, Path::new(&name));
+                    }
                     self.cwd = path.to_path_buf();
                 } else if path.is_dir() {
                     self.listings.insert(path.to_path_buf(), MyModel::list_dir(path)?); //maybe move this
                     self.cwd = path.to_path_buf();
                 }
                 println!("path exists on fs");
-                return Ok(self.listings.get(path).expect("This path should have just been added to listings.").clone());
+                self.read_sidecars(path);
+                let listing = self.listings.get(path).ok_or_else(|| format!("{} can't be listed", path.display()))?;
+                return Ok(listing.clone());
             },
 
             Err(err) if err.kind() == ErrorKind::NotADirectory => {
                 println!("maybe archive path inside another archive");
                 if let Some(sub_ext) = path.extension() {
-                    if sub_ext.eq_ignore_ascii_case("zip") { //can only list zipfiles from other archives
+                    if is_zip(sub_ext) { //can only list zipfiles from other archives
                         if let Some((maybe_archive, Some(sub_path))) = self.locate_resource(path) {
                             if let Ok(true) = maybe_archive.try_exists() {
                                 //extract from archive in fs and list
@@ -282,6 +1008,8 @@ impl MyModel {
                                 if let Some(a) = self.data_cache.get(path) {
                                     let list = MyModel::get_zip_filelist(a.clone()); //this clone is no good
                                     self.add_filelist_to_directory(list, path.to_path_buf());
+                                    self.listings.entry(path.to_path_buf()).or_default(); //an empty archive
+                                    self.read_sidecars(path);
                                     self.cwd = path.to_path_buf();
                                     return Ok(self.listings.get(path).expect("Path was found in listings.").clone());
                                 }
@@ -297,42 +1025,112 @@ impl MyModel {
             },
         }
 
-        let yay: Vec<Listing> = vec![
-            Listing {
-                entry_type: EntryType::File,
-                display_name: String::from("not yay"),
-                file_path: PathBuf::from("1"),
-                size: 666
-            }
-        ];
-        self.cwd = path.to_path_buf();
-        Ok(yay)
+        Err(format!("{} can't be listed", path.display()).into())
     }
 
     fn extract_from_archive(&mut self, archive_path: &Path, sub_path: &Path) {
-        if let Some(ex) = archive_path.extension() {
-            if ex.eq_ignore_ascii_case("zip") { //use zip
-                let f = File::open(archive_path).expect("unable to load file");
-                let buf_read = BufReader::new(f);
-                let mut archive = zip::ZipArchive::new(buf_read).unwrap();
-                if let Ok(mut file) = archive.by_name(sub_path.to_str().unwrap()) {
-                    let mut content = vec![];
-                    if let Ok(_size) = io::copy(&mut file, &mut content) {
-                        let name = archive_path.join(sub_path);
-                        self.data_in_cache_size += content.len();
-                        self.data_cache.insert(name, content);
-                    }   
-                }   
-            } else { //use archive-reader
-                let arc = Archive::open(archive_path);
-                let mut content = vec![];
-                if let Ok(_size) = arc.read_file(sub_path.to_str().unwrap(), &mut content) {
-                    let name = archive_path.join(sub_path);
-                    self.data_in_cache_size += content.len();
-                    self.data_cache.insert(name, content);
+        if let Some(content) = MyModel::read_archive_entry(archive_path, sub_path) {
+            let name = archive_path.join(sub_path);
+            self.data_in_cache_size += content.len();
+            self.data_cache.insert(name, content);
+        }
+    }
+
+    fn read_archive_entry(archive_path: &Path, sub_path: &Path) -> Option<Vec<u8>> {
+        let ex = archive_path.extension()?;
+        let mut content = vec![];
+        if is_zip(ex) { //use zip
+            let f = File::open(archive_path).ok()?;
+            let buf_read = BufReader::new(f);
+            let mut archive = zip::ZipArchive::new(buf_read).ok()?;
+            let mut file = archive.by_name(sub_path.to_str()?).ok()?;
+            io::copy(&mut file, &mut content).ok()?;
+        } else { //use archive-reader
+            let arc = Archive::open(archive_path);
+            arc.read_file(sub_path.to_str()?, &mut content).ok()?;
+        }
+        Some(content)
+    }
+
+    ///file data from disk, or from an archive on disk, without caching it
+    pub fn read_file_data(path: &Path) -> Option<Vec<u8>> {
+        if path.is_file() {
+            return fs::read(path).ok();
+        }
+        let archive = path.ancestors().skip(1).find(|ancestor| ancestor.is_file())?;
+        MyModel::read_archive_entry(archive, path.strip_prefix(archive).ok()?)
+    }
+
+    ///images in a folder and its subfolders, or in an archive, found by extension
+    pub fn collect_images(root: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut images = Vec::new();
+        if root.is_file() {
+            for name in MyModel::get_filelist(root)? {
+                if !name.ends_with('/') && has_image_extension(Path::new(&name)) {
+                    images.push(root.join(name));
+                }
+            }
+        } else {
+            let mut dirs = vec![root.to_path_buf()];
+            while let Some(dir) = dirs.pop() {
+                let Ok(entries) = fs::read_dir(&dir) else { continue }; //unreadable subfolders are skipped
+                for entry in entries.flatten() {
+                    let Ok(file_type) = entry.file_type() else { continue };
+                    if file_type.is_dir() {
+                        dirs.push(entry.path());
+                    } else if file_type.is_file() && has_image_extension(&entry.path()) {
+                        images.push(entry.path());
+                    }
                 }
             }
         }
+        images.sort();
+        Ok(images)
+    }
+
+    ///hands the data of each image to the closure, an archive is read through once instead of once per image
+    pub fn read_images(root: &Path, images: &[PathBuf], mut each: impl FnMut(PathBuf, Vec<u8>)) -> Result<(), Box<dyn Error>> {
+        if !root.is_file() {
+            for path in images {
+                if let Ok(data) = fs::read(path) { //gone since listing
+                    each(path.clone(), data);
+                }
+            }
+        } else if root.extension().is_some_and(is_zip) {
+            let mut archive = zip::ZipArchive::new(BufReader::new(File::open(root)?))?;
+            for path in images {
+                let Some(name) = path.strip_prefix(root).ok().and_then(Path::to_str) else { continue };
+                let Ok(mut file) = archive.by_name(name) else { continue };
+                let mut data = Vec::new();
+                io::copy(&mut file, &mut data)?;
+                each(path.clone(), data);
+            }
+        } else {
+            let wanted: HashSet<&Path> = images.iter().map(PathBuf::as_path).collect();
+            Archive::open(root).entries(|entry| {
+                let path = root.join(&*entry.file_name()?);
+                if wanted.contains(path.as_path()) {
+                    let mut data = Vec::new();
+                    entry.read_file(&mut data)?;
+                    each(path, data);
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    ///the first frame or layer of any supported image, for work done on other threads
+    pub fn decode_still(image_data: &[u8], image_pb: &Path) -> Result<DynamicImage, Box<dyn Error>> {
+        match MyModel::sniff_image_kind(image_data).or_else(|| MyModel::image_kind_from_extension(image_pb)) {
+            Some(ImageKind::Heif) => MyModel::decode_heif(image_data),
+            Some(ImageKind::Jxl) => MyModel::decode_jxl(image_data, 1)?.0.into_iter().next().map(|(image, _)| image).ok_or_else(|| "No frames in the image".into()),
+            Some(ImageKind::Svg) => Ok(MyModel::rasterize_svg(image_data, 1.)?.0),
+            Some(ImageKind::Raw) => my_raw::decode_raw(image_data),
+            Some(ImageKind::Psd) => my_layers::decode_psd(image_data)?.into_iter().next().map(|(_, image)| image).ok_or_else(|| "No layers in the image".into()),
+            Some(ImageKind::Exr) => my_layers::decode_exr(image_data)?.into_iter().next().map(|(_, image)| image).ok_or_else(|| "No layers in the image".into()),
+            None => Ok(image::load_from_memory(image_data)?),
+        }
     }
 
     fn extract_from_data_cache(&mut self, archive_path: &Path, sub_path: &Path) {
@@ -356,9 +1154,12 @@ impl MyModel {
     fn get_filelist(archive_path: &Path) -> Result<Vec<String>, Box<dyn Error>> {
         let mut res: Vec<String> = Vec::new();
 
+        if !has_archive_extension(archive_path) {
+            return Err(format!("{} is not an archive", archive_path.display()).into());
+        }
         if let Some(ex) = archive_path.extension() {
-            if ex.eq_ignore_ascii_case("zip") { //use zip
-                let f = File::open(archive_path).expect("unable to load file");
+            if is_zip(ex) { //use zip
+                let f = File::open(archive_path)?;
                 let buf_read = BufReader::new(f);
                 let archive = zip::ZipArchive::new(buf_read)?;
                 for name in archive.file_names() {
@@ -368,13 +1169,64 @@ impl MyModel {
                 let mut arc = Archive::open(archive_path);
                 res = arc
                     .block_size(1024*1024)
-                    .list_file_names().expect("no files")
+                    .list_file_names()?
                     .collect::<Result<Vec<_>>>()?;
             }
         }
         Ok(res)
     }
 
+    ///reads ComicInfo.xml from the archive and puts the pages in its reading order
+    fn read_comic_info(&mut self, archive_path: &Path, info_path: &Path) {
+        self.extract_from_archive(archive_path, info_path);
+        if let Some(data) = self.data_cache.remove(&archive_path.join(info_path)) {
+            self.data_in_cache_size -= data.len();
+            let info = ComicInfo::parse(&String::from_utf8_lossy(&data));
+            self.apply_page_order(archive_path, &info.pages);
+            self.comic_info.insert(archive_path.to_path_buf(), info);
+        }
+    }
+
+    ///pages are the folder in the archive with most images, sorted by name unless the page list says otherwise
+    fn apply_page_order(&mut self, archive_path: &Path, pages: &[usize]) {
+        let mut pages_folder = None;
+        let mut most_images = 0;
+        for (folder, list) in &self.listings {
+            if folder.starts_with(archive_path) {
+                let images = list.iter().filter(|l| l.entry_type == EntryType::Image).count();
+                if images > most_images {
+                    most_images = images;
+                    pages_folder = Some(folder.clone());
+                }
+            }
+        }
+
+        if let Some(list) = pages_folder.and_then(|folder| self.listings.get_mut(&folder)) {
+            let (mut images, others): (Vec<Listing>, Vec<Listing>) = list.drain(..).partition(|l| l.entry_type == EntryType::Image);
+            images.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+
+            let mut used = vec![false; images.len()];
+            let mut ordered = others;
+            for &page in pages {
+                if page < images.len() && !used[page] {
+                    used[page] = true;
+                    ordered.push(images[page].clone());
+                }
+            }
+            for (n, image) in images.into_iter().enumerate() {
+                if !used[n] { //pages missing from the list go last
+                    ordered.push(image);
+                }
+            }
+            *list = ordered;
+        }
+    }
+
+    ///comic info for an archive or anything inside it
+    pub fn get_comic_info(&self, path: &Path) -> Option<&ComicInfo> {
+        path.ancestors().find_map(|ancestor| self.comic_info.get(ancestor))
+    }
+
     fn get_zip_filelist(archive: Vec<u8>) -> Vec<String> {
         let mut res: Vec<String> = Vec::new();
         let buf_read = Cursor::new(archive);
@@ -503,24 +1355,42 @@ impl MyModel {
                     }
                 }
             }
+            //only files without an extension are looked into, opening every file is slow on large folders
+            if item.entry_type == EntryType::File && item.file_path.extension().is_none() && MyModel::has_image_signature(&item.file_path, false) {
+                item.entry_type = EntryType::Image;
+            }
         }
         list
     }
 
+    ///all images in the current listing
+    pub fn get_image_list(&self) -> Vec<PathBuf> {
+        let mut images = Vec::new();
+        if let Some(current_listing) = self.current_listing() {
+            for listing in current_listing {
+                if listing.entry_type == EntryType::Image {
+                    images.push(listing.file_path.clone());
+                }
+            }
+        }
+        images
+    }
+
+    ///returns the index in the current listing
+    pub fn get_index(&self, pb: &PathBuf) -> Option<usize> {
+        self.current_listing()?.iter().position(|listing| listing.file_path.eq(pb))
+    }
+
     ///returns pathbuf and index for next image
     pub fn get_next_image(&self, cur: PathBuf) -> Option<(PathBuf, usize)> { //what kind of inefficiency is this function
-        if let Some(current_listing) = self.listings.get(&self.cwd) {
+        if let Some(current_listing) = self.current_listing() {
             for (pos, listing) in current_listing.iter().enumerate() {
                 if listing.file_path.eq(&cur) {
                     let remaning = &current_listing[pos+1..]; // from next
                     for (pos2, list_entry) in remaning.iter().enumerate() {
-                        if let Some(ex) = list_entry.file_path.extension() {
-                            for imgex in IMAGE_EXTENSIONS {
-                                if ex.eq_ignore_ascii_case(imgex) {
-                                    return Some((list_entry.file_path.clone(), pos+1 + pos2)); //remaning began from +1
-                                }
-                            }
-                        }   
+                        if list_entry.entry_type == EntryType::Image {
+                            return Some((list_entry.file_path.clone(), pos+1 + pos2)); //remaning began from +1
+                        }
                     }
                 }
             }
@@ -530,17 +1400,13 @@ impl MyModel {
 
     ///returns pathbuf and index for prev image
     pub fn get_prev_image(&self, cur: PathBuf) -> Option<(PathBuf, usize)> { //what kind of inefficiency is this function
-        if let Some(current_listing) = self.listings.get(&self.cwd) {
+        if let Some(current_listing) = self.current_listing() {
             for (pos, listing) in current_listing.iter().enumerate() {
                 if listing.file_path.eq(&cur) {
                     let preceding = &current_listing[0..pos]; // from -1 really, but including current because len() is 1 based
                     for n in (0..preceding.len()).rev() { //is there a usable next_back?
-                        if let Some(ex) = preceding[n].file_path.extension() {
-                            for imgex in IMAGE_EXTENSIONS {
-                                if ex.eq_ignore_ascii_case(imgex) {
-                                    return Some((preceding[n].file_path.clone(), pos - (preceding.len()-n) ));
-                                }
-                            }
+                        if preceding[n].entry_type == EntryType::Image {
+                            return Some((preceding[n].file_path.clone(), pos - (preceding.len()-n) ));
                         }
                     }
                 }
@@ -550,3 +1416,15 @@ impl MyModel {
     }
 
 }
+
+fn has_image_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| IMAGE_EXTENSIONS.iter().any(|imagex| ext.eq_ignore_ascii_case(imagex)))
+}
+
+fn has_archive_extension(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ARCHIVE_EXTENSIONS.iter().any(|archex| ext.eq_ignore_ascii_case(archex)))
+}
+
+fn is_zip(ext: &OsStr) -> bool {
+    ZIP_EXTENSIONS.iter().any(|zipex| ext.eq_ignore_ascii_case(zipex))
+}
@@ -0,0 +1,222 @@
+pub const RAW_EXTENSIONS: [&str; 8] = ["cr2", "cr3", "nef", "arw", "dng", "orf", "rw2", "raf"];
+const RAF_MAGIC: &[u8] = b"FUJIFILMCCD-RAW";
+const CR3_BRAND: &[u8; 4] = b"crx ";
+const CR3_METADATA_BOX: &[u8; 4] = b"CMT1"; //holds ifd0 with the orientation
+const RW2_JPEG_FROM_RAW: u16 = 0x2E;
+const COMPRESSION_NONE: u32 = 1;
+const COMPRESSION_OLD_JPEG: u32 = 6;
+const COMPRESSION_JPEG: u32 = 7;
+const PHOTOMETRIC_RGB: u32 = 2;
+const PHOTOMETRIC_YCBCR: u32 = 6;
+
+use std::{error::Error, io::Cursor};
+
+use image::{DynamicImage, ImageBuffer, ImageFormat, ImageReader, Rgb};
+
+use crate::my_tiff::{self, Entry, Tiff};
+
+pub fn is_raw_data(data: &[u8]) -> bool {
+    data.starts_with(RAF_MAGIC) || (data.len() >= 12 && &data[4..8] == b"ftyp" && &data[8..12] == CR3_BRAND)
+}
+
+///camera raw files are not demosaiced, the biggest embedded jpeg is shown instead
+///dng files that carry a ready rgb image are shown at full size
+pub fn decode_raw(data: &[u8]) -> Result<DynamicImage, Box<dyn Error>> {
+    let mut full_size = None;
+    let mut previews: Vec<&[u8]> = Vec::new();
+    let mut orientation = None;
+
+    if data.starts_with(RAF_MAGIC) {
+        //the header points to a jpeg, the orientation lives in its exif
+        let offset = u32::from_be_bytes(data.get(84..88).ok_or("raf header too short")?.try_into()?) as usize;
+        let len = u32::from_be_bytes(data.get(88..92).ok_or("raf header too short")?.try_into()?) as usize;
+        if let Some(jpeg) = data.get(offset..offset + len) {
+            previews.push(jpeg);
+        }
+    } else if let Some(tiff) = Tiff::new(data, 0) {
+        let ifds = tiff.all_ifds();
+        orientation = ifds.first().and_then(|ifd| orientation_of(&tiff, ifd));
+        for ifd in &ifds {
+            if full_size.is_none() {
+                full_size = full_size_image(&tiff, ifd);
+            }
+            previews.extend(ifd_previews(&tiff, ifd));
+        }
+    } else if let Some(pos) = find(data, CR3_METADATA_BOX) {
+        if let Some(tiff) = Tiff::new(data, pos + 4) {
+            orientation = tiff.first_ifd().and_then(|ifd| tiff.read_ifd(ifd)).and_then(|(ifd, _)| orientation_of(&tiff, &ifd));
+        }
+    }
+
+    //cr3 and anything the directories didn't lead to
+    if previews.is_empty() {
+        previews = scan_jpegs(data);
+    }
+
+    let image = match full_size {
+        Some(image) => image,
+        None => {
+            previews.sort_by_key(|jpeg| std::cmp::Reverse(jpeg.len())); //bigger files are bigger images
+            let mut decoded = None;
+            for jpeg in previews {
+                if let Ok(image) = ImageReader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg).decode() {
+                    if orientation.is_none() {
+                        orientation = jpeg_orientation(jpeg);
+                    }
+                    decoded = Some(image);
+                    break;
+                }
+            }
+            decoded.ok_or("no embedded preview found")?
+        },
+    };
+
+    Ok(apply_orientation(image, orientation.unwrap_or(1)))
+}
+
+fn orientation_of(tiff: &Tiff, ifd: &[Entry]) -> Option<u32> {
+    my_tiff::find(ifd, my_tiff::TAG_ORIENTATION).and_then(|entry| tiff.value(entry))
+}
+
+fn jpeg_orientation(jpeg: &[u8]) -> Option<u32> {
+    let tiff = Tiff::new(jpeg, my_tiff::jpeg_exif_base(jpeg)?)?;
+    let (ifd, _) = tiff.read_ifd(tiff.first_ifd()?)?;
+    orientation_of(&tiff, &ifd)
+}
+
+///jpegs pointed to by a directory, either as thumbnail fields or as a single jpeg strip
+fn ifd_previews<'a>(tiff: &Tiff<'a>, ifd: &[Entry]) -> Vec<&'a [u8]> {
+    let mut res = Vec::new();
+    let value = |tag| my_tiff::find(ifd, tag).and_then(|entry| tiff.value(entry));
+
+    if let (Some(offset), Some(len)) = (value(my_tiff::TAG_JPEG_OFFSET), value(my_tiff::TAG_JPEG_LENGTH)) {
+        res.extend(tiff.slice(offset, len));
+    }
+    if let Some(compression) = value(my_tiff::TAG_COMPRESSION) {
+        if compression == COMPRESSION_OLD_JPEG || compression == COMPRESSION_JPEG {
+            if let Some(strip) = single_strip(tiff, ifd) {
+                res.push(strip);
+            }
+        }
+    }
+    if let Some(entry) = my_tiff::find(ifd, RW2_JPEG_FROM_RAW) {
+        res.extend(tiff.bytes(entry));
+    }
+
+    //lossless jpeg raw data starts with a jpeg marker too but the image crate refuses it
+    res.retain(|jpeg| jpeg.starts_with(&[0xFF, 0xD8]));
+    res
+}
+
+fn single_strip<'a>(tiff: &Tiff<'a>, ifd: &[Entry]) -> Option<&'a [u8]> {
+    let offsets = tiff.values(my_tiff::find(ifd, my_tiff::TAG_STRIP_OFFSETS)?);
+    let counts = tiff.values(my_tiff::find(ifd, my_tiff::TAG_STRIP_BYTE_COUNTS)?);
+    if offsets.len() == 1 && counts.len() == 1 {
+        tiff.slice(offsets[0], counts[0])
+    } else {
+        None
+    }
+}
+
+///the main image of a dng when it is already rgb, mosaic and linear data need a raw developer
+fn full_size_image(tiff: &Tiff, ifd: &[Entry]) -> Option<DynamicImage> {
+    let value = |tag| my_tiff::find(ifd, tag).and_then(|entry| tiff.value(entry));
+
+    if value(my_tiff::TAG_NEW_SUBFILE_TYPE).unwrap_or(0) != 0 {
+        return None; //a reduced resolution preview
+    }
+    let width = value(my_tiff::TAG_IMAGE_WIDTH)?;
+    let height = value(my_tiff::TAG_IMAGE_HEIGHT)?;
+
+    match (value(my_tiff::TAG_COMPRESSION)?, value(my_tiff::TAG_PHOTOMETRIC)?) {
+        (COMPRESSION_JPEG, PHOTOMETRIC_RGB | PHOTOMETRIC_YCBCR) => {
+            let jpeg = single_strip(tiff, ifd)?;
+            ImageReader::with_format(Cursor::new(jpeg), ImageFormat::Jpeg).decode().ok()
+        },
+
+        (COMPRESSION_NONE, PHOTOMETRIC_RGB) => {
+            if value(my_tiff::TAG_SAMPLES_PER_PIXEL)? != 3 {
+                return None;
+            }
+            let bits = value(my_tiff::TAG_BITS_PER_SAMPLE)?;
+            let offsets = tiff.values(my_tiff::find(ifd, my_tiff::TAG_STRIP_OFFSETS)?);
+            let counts = tiff.values(my_tiff::find(ifd, my_tiff::TAG_STRIP_BYTE_COUNTS)?);
+            let mut raw = Vec::new();
+            for (offset, count) in offsets.into_iter().zip(counts) {
+                raw.extend_from_slice(tiff.slice(offset, count)?);
+            }
+
+            match bits {
+                8 => ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, raw).map(DynamicImage::ImageRgb8),
+                16 => {
+                    let samples = (0..raw.len() / 2).map(|n| tiff.u16_of(&raw[n * 2..n * 2 + 2])).collect();
+                    ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, samples).map(DynamicImage::ImageRgb16)
+                },
+                _ => None,
+            }
+        },
+
+        _ => None,
+    }
+}
+
+///every complete jpeg in the data, for containers without tiff directories
+fn scan_jpegs(data: &[u8]) -> Vec<&[u8]> {
+    let mut res = Vec::new();
+    let mut pos = 0;
+    while let Some(found) = find(&data[pos..], &[0xFF, 0xD8, 0xFF]) {
+        let start = pos + found;
+        match jpeg_end(data, start) {
+            Some(end) => {
+                res.push(&data[start..end]);
+                pos = end;
+            },
+            None => pos = start + 1,
+        }
+    }
+    res
+}
+
+///walks the marker segments and the entropy coded data up to the end of image marker
+fn jpeg_end(data: &[u8], start: usize) -> Option<usize> {
+    let mut pos = start + 2;
+    loop {
+        if *data.get(pos)? != 0xFF {
+            return None;
+        }
+        let marker = *data.get(pos + 1)?;
+        match marker {
+            0xD9 => return Some(pos + 2),
+            0xFF => pos += 1, //fill byte
+            0x01 | 0xD0..=0xD7 => pos += 2,
+            _ => {
+                let len = u16::from_be_bytes([*data.get(pos + 2)?, *data.get(pos + 3)?]) as usize;
+                pos += 2 + len;
+                if marker == 0xDA {
+                    //skip scan data, stuffed zeros and restart markers belong to it
+                    while !(*data.get(pos)? == 0xFF && !matches!(*data.get(pos + 1)?, 0x00 | 0xD0..=0xD7)) {
+                        pos += 1;
+                    }
+                }
+            },
+        }
+    }
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+///exif orientation values, 1 is upright
+fn apply_orientation(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
@@ -0,0 +1,304 @@
+const DIALOG_W: i32 = 600;
+const DIALOG_H: i32 = 440;
+const LABEL_W: i32 = 120;
+const ROW_H: i32 = 25;
+const EXIF_READ_LIMIT: u64 = 1 << 20; //exif is near the start of jpegs and camera raw files
+const MAX_COUNTER_WIDTH: usize = 12;
+const READING_EXIF: &str = "Reading EXIF...";
+const DEFAULT_TEMPLATE: &str = "{date}_{n:3}.{ext}";
+const TEMPLATE_TOOLTIP: &str = "{n} counter, {n:3} pads it to 3 digits, up to 12\n{name} original name without extension\n{ext} original extension\n{date} and {time} when taken, from EXIF\n{camera} camera make and model, from EXIF";
+
+use std::{cell::{Cell, RefCell}, collections::{HashMap, HashSet}, fs::File, io::Read, path::{Path, PathBuf}, rc::Rc, sync::mpsc, thread};
+
+use fltk::{app, browser::HoldBrowser, button::Button, enums::{Align, CallbackTrigger}, frame::Frame, group::Flex, input::{Input, IntInput}, prelude::{BrowserExt, GroupExt, InputExt, WidgetBase, WidgetExt, WindowExt}, window::Window};
+
+use crate::{my_tiff::{self, Tiff}, screen_center};
+
+///what a template can use of one file
+struct RenameSource {
+    path: PathBuf,
+    exif: Option<Exif>, //none until read on a thread
+}
+
+#[derive(Default)]
+struct Exif {
+    date: Option<String>, //2024-05-17
+    time: Option<String>, //140322
+    camera: Option<String>,
+}
+
+///the new name of one file, or why it can't be used
+struct Planned {
+    new_name: String,
+    problem: Option<String>,
+}
+
+impl Exif {
+    fn read(path: &Path) -> Self {
+        let mut source = Exif::default();
+        let mut data = Vec::new();
+        if File::open(path).and_then(|file| file.take(EXIF_READ_LIMIT).read_to_end(&mut data)).is_err() {
+            return source;
+        }
+        let base = if data.starts_with(&[0xFF, 0xD8]) { my_tiff::jpeg_exif_base(&data) } else { Some(0) };
+        let Some(tiff) = base.and_then(|base| Tiff::new(&data, base)) else { return source };
+
+        let ifds = tiff.all_ifds();
+        let text = |tag| ifds.iter().find_map(|entries| my_tiff::find(entries, tag).and_then(|entry| tiff.ascii(entry)));
+
+        //"2024:05:17 14:03:22"
+        if let Some((date, time)) = text(my_tiff::TAG_DATE_TIME_ORIGINAL).or_else(|| text(my_tiff::TAG_DATE_TIME))
+            .and_then(|taken| taken.split_once(' ').map(|(date, time)| (date.replace(':', "-"), time.replace(':', ""))))
+            .filter(|(date, time)| date.len() == 10 && !date.starts_with("0000") && time.len() == 6) {
+            source.date = Some(date);
+            source.time = Some(time);
+        }
+
+        //the model usually starts with the make, Canon and Canon EOS R5, but not always, SONY and ILCE-7M3
+        source.camera = match (text(my_tiff::TAG_MAKE), text(my_tiff::TAG_MODEL)) {
+            (Some(make), Some(model)) => {
+                let brand = make.split_whitespace().next().unwrap_or_default().to_lowercase();
+                if model.to_lowercase().starts_with(&brand) { Some(model) } else { Some(format!("{make} {model}")) }
+            },
+            (make, model) => model.or(make),
+        }.map(|camera| camera.replace('/', "-"));
+        source
+    }
+}
+
+impl RenameSource {
+    fn exif(&self) -> Result<&Exif, &'static str> {
+        self.exif.as_ref().ok_or(READING_EXIF)
+    }
+
+    ///fills in the fields of the template
+    fn expand(&self, template: &str, counter: u32) -> Result<String, String> {
+        let name = self.path.file_name().unwrap_or_default().to_string_lossy();
+        let (stem, ext) = match name.rsplit_once('.') {
+            Some((stem, ext)) if !stem.is_empty() && !self.path.is_dir() => (stem, ext),
+            _ => (&*name, ""),
+        };
+
+        let mut res = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            res.push_str(&rest[..start]);
+            let end = start + rest[start..].find('}').ok_or("A { is not closed")?;
+            let field = &rest[start + 1..end];
+            let value = match field.split_once(':') {
+                Some(("n", width)) => {
+                    let width: usize = width.parse().map_err(|_| format!("{{{field}}} needs a number of digits"))?;
+                    if !(1..=MAX_COUNTER_WIDTH).contains(&width) {
+                        return Err(format!("{{{field}}} takes 1 to {MAX_COUNTER_WIDTH} digits"));
+                    }
+                    format!("{counter:0width$}")
+                },
+                Some(_) => return Err(format!("Only {{n}} takes digits, not {{{field}}}")),
+                None => match field {
+                    "n" => counter.to_string(),
+                    "name" => stem.to_string(),
+                    "ext" => ext.to_string(),
+                    "date" => self.exif()?.date.clone().ok_or("No EXIF date")?,
+                    "time" => self.exif()?.time.clone().ok_or("No EXIF date")?,
+                    "camera" => self.exif()?.camera.clone().ok_or("No EXIF camera")?,
+                    _ => return Err(format!("Unknown field {{{field}}}")),
+                },
+            };
+            res.push_str(&value);
+            rest = &rest[end + 1..];
+        }
+        res.push_str(rest);
+        Ok(res.trim().to_string())
+    }
+}
+
+///new names in order, with invalid names, names used twice and names of other files flagged
+fn plan(sources: &[RenameSource], template: &str, start: u32) -> Vec<Planned> {
+    let mut planned: Vec<Planned> = sources.iter().enumerate()
+        .map(|(n, source)| match source.expand(template, start.saturating_add(n as u32)) {
+            Ok(new_name) => Planned { new_name, problem: None },
+            Err(problem) => Planned { new_name: String::new(), problem: Some(problem) },
+        })
+        .collect();
+
+    let renamed: HashSet<&Path> = sources.iter().map(|source| source.path.as_path()).collect();
+    let mut uses: HashMap<PathBuf, usize> = HashMap::new();
+    for (source, item) in sources.iter().zip(&planned) {
+        if item.problem.is_none() {
+            *uses.entry(source.path.with_file_name(&item.new_name)).or_default() += 1;
+        }
+    }
+
+    for (source, item) in sources.iter().zip(planned.iter_mut()) {
+        if item.problem.is_some() {
+            continue;
+        }
+        let name = &item.new_name;
+        let to = source.path.with_file_name(name);
+        item.problem = if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            Some("Not a valid file name".to_string())
+        } else if uses.get(&to).copied().unwrap_or_default() > 1 {
+            Some("Same name as another file here".to_string())
+        } else if !renamed.contains(to.as_path()) && to.symlink_metadata().is_ok() {
+            Some("Already exists".to_string())
+        } else {
+            None
+        };
+    }
+    planned
+}
+
+///asks for a name template and previews the new names, returns the files whose name changes, none when cancelled
+pub fn ask_batch_rename(paths: &[PathBuf]) -> Option<Vec<(PathBuf, String)>> {
+    let sources: Vec<RenameSource> = paths.iter().map(|path| RenameSource { path: path.clone(), exif: None }).collect();
+    //up to a megabyte pr file, read while the dialog is up
+    let (exif_tx, exif_rx) = mpsc::channel();
+    let to_read = paths.to_vec();
+    thread::spawn(move || {
+        let exif: Vec<Exif> = to_read.iter().map(|path| Exif::read(path)).collect();
+        if exif_tx.send(exif).is_ok() {
+            app::awake();
+        }
+    });
+
+    let mut win = Window::default()
+        .with_size(DIALOG_W, DIALOG_H)
+        .with_pos(screen_center().0 - DIALOG_W / 2, screen_center().1 - DIALOG_H / 2)
+        .with_label(&format!("Rename {} files", paths.len()));
+
+    let mut col = Flex::default_fill().column();
+        col.set_margin(10);
+        col.set_spacing(5);
+
+        let row = labeled_row(&mut col, "Template:");
+            let mut template = Input::default();
+            template.set_value(DEFAULT_TEMPLATE);
+            template.set_tooltip(TEMPLATE_TOOLTIP);
+        row.end();
+
+        let row = labeled_row(&mut col, "Counter starts at:");
+            let mut start = IntInput::default();
+            start.set_value("1");
+        row.end();
+
+        let mut preview = HoldBrowser::default();
+        preview.set_column_widths(&[200, 200]); //old name, new name and problem
+
+        let status = Frame::default().with_align(Align::Left | Align::Inside);
+        col.fixed(&status, ROW_H);
+
+        let mut row = Flex::default().row();
+            Frame::default();
+            let mut btn_rename = Button::default().with_label("Rename");
+            let mut btn_cancel = Button::default().with_label("Cancel");
+            row.fixed(&btn_rename, 80);
+            row.fixed(&btn_cancel, 80);
+        row.end();
+        col.fixed(&row, ROW_H);
+    col.end();
+    win.end();
+    win.make_modal(true);
+
+    let sources = Rc::new(RefCell::new(sources));
+    let mut update_preview = {
+        let (template, start, sources) = (template.clone(), start.clone(), sources.clone());
+        let (mut preview, mut status, mut btn_rename, mut win) = (preview.clone(), status.clone(), btn_rename.clone(), win.clone());
+        move || {
+            let sources = sources.borrow();
+            let planned = plan(&sources, &template.value(), parse_start(&start));
+            preview.clear();
+            let (mut problems, mut changes) = (0, 0);
+            for (source, item) in sources.iter().zip(&planned) {
+                let old_name = source.path.file_name().unwrap_or_default().to_string_lossy().to_string();
+                let note = match &item.problem {
+                    Some(problem) => {
+                        problems += 1;
+                        format!("@C1{problem}") //red
+                    },
+                    None if item.new_name == old_name => "unchanged".to_string(),
+                    None => {
+                        changes += 1;
+                        String::new()
+                    },
+                };
+                preview.add(&format!("{old_name}\t{}\t{note}", item.new_name));
+            }
+            if planned.iter().any(|item| item.problem.as_deref() == Some(READING_EXIF)) {
+                status.set_label(READING_EXIF);
+                btn_rename.deactivate();
+            } else if problems > 0 {
+                status.set_label(&format!("{problems} conflicts, change the template to rename"));
+                btn_rename.deactivate();
+            } else if changes == 0 {
+                status.set_label("No names change");
+                btn_rename.deactivate();
+            } else {
+                status.set_label(&format!("{changes} files will be renamed, undo with Ctrl+z"));
+                btn_rename.activate();
+            }
+            win.redraw();
+        }
+    };
+    update_preview();
+    template.set_trigger(CallbackTrigger::Changed);
+    template.set_callback({
+        let mut update_preview = update_preview.clone();
+        move |_| update_preview()
+    });
+    start.set_trigger(CallbackTrigger::Changed);
+    start.set_callback({
+        let mut update_preview = update_preview.clone();
+        move |_| update_preview()
+    });
+
+    let accepted = Rc::new(Cell::new(false));
+    btn_rename.set_callback({
+        let accepted = accepted.clone();
+        let mut win = win.clone();
+        move |_| {
+            accepted.set(true);
+            win.hide();
+        }
+    });
+    btn_cancel.set_callback({
+        let mut win = win.clone();
+        move |_| win.hide()
+    });
+
+    win.show();
+    while win.shown() {
+        app::wait();
+        if let Ok(exif) = exif_rx.try_recv() {
+            for (source, exif) in sources.borrow_mut().iter_mut().zip(exif) {
+                source.exif = Some(exif);
+            }
+            update_preview();
+        }
+    }
+    if !accepted.get() {
+        return None;
+    }
+
+    let sources = sources.borrow();
+    let planned = plan(&sources, &template.value(), parse_start(&start));
+    if planned.iter().any(|item| item.problem.is_some()) {
+        return None;
+    }
+    Some(sources.iter().zip(planned)
+        .filter(|(source, item)| source.path.file_name().is_some_and(|name| name.to_string_lossy() != item.new_name))
+        .map(|(source, item)| (source.path.clone(), item.new_name))
+        .collect())
+}
+
+///a row with a fixed width label in front of the widget added next
+fn labeled_row(col: &mut Flex, label: &str) -> Flex {
+    let mut row = Flex::default().row();
+    let frame = Frame::default().with_label(label).with_align(Align::Left | Align::Inside);
+    row.fixed(&frame, LABEL_W);
+    col.fixed(&row, ROW_H);
+    row
+}
+
+fn parse_start(start: &IntInput) -> u32 {
+    start.value().trim().parse().unwrap_or(1)
+}
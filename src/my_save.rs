@@ -0,0 +1,163 @@
+const DIALOG_W: i32 = 340;
+const DIALOG_H: i32 = 260;
+const LABEL_W: i32 = 110;
+const ROW_H: i32 = 25;
+
+use std::{cell::Cell, rc::Rc};
+
+use fltk::{app, button::{Button, CheckButton}, dialog::alert, frame::Frame, group::Flex, input::IntInput, menu::Choice, misc::Spinner, prelude::{GroupExt, InputExt, MenuExt, WidgetBase, WidgetExt, WindowExt}, window::Window};
+
+use crate::{my_export::{ExportFormat, ExportOptions, PngCompression, DEFAULT_QUALITY}, screen_center};
+
+///asks for format, quality, compression, size and metadata, none when cancelled
+pub fn ask_save_options(image_size: (u32, u32), has_metadata: bool) -> Option<ExportOptions> {
+    let mut win = Window::default()
+        .with_size(DIALOG_W, DIALOG_H)
+        .with_pos(screen_center().0 - DIALOG_W / 2, screen_center().1 - DIALOG_H / 2)
+        .with_label("Save as");
+
+    let mut col = Flex::default_fill().column();
+        col.set_margin(10);
+        col.set_spacing(5);
+
+        let row = labeled_row(&mut col, "Format:");
+            let mut format = Choice::default();
+            format.add_choice("PNG|JPEG|WebP");
+            format.set_value(0);
+        row.end();
+
+        let row = labeled_row(&mut col, "Quality:");
+            let mut quality = Spinner::default();
+            quality.set_range(1., 100.);
+            quality.set_step(1.);
+            quality.set_value(DEFAULT_QUALITY as f64);
+            quality.set_tooltip("JPEG and WebP, WebP is lossless at 100.");
+        row.end();
+
+        let row = labeled_row(&mut col, "PNG compression:");
+            let mut compression = Choice::default();
+            compression.add_choice("Fast|Default|Best");
+            compression.set_value(1);
+        row.end();
+
+        let mut row = Flex::default().row();
+            let mut resize = CheckButton::default().with_label("Fit within");
+            row.fixed(&resize, LABEL_W);
+            let mut width = IntInput::default();
+            width.set_value(&image_size.0.to_string());
+            let times = Frame::default().with_label("x");
+            row.fixed(&times, 15);
+            let mut height = IntInput::default();
+            height.set_value(&image_size.1.to_string());
+        row.end();
+        col.fixed(&row, ROW_H);
+
+        let mut metadata = CheckButton::default().with_label("Keep metadata (ICC profile and EXIF)");
+        metadata.set_checked(has_metadata);
+        if !has_metadata {
+            metadata.deactivate();
+            metadata.set_tooltip("No metadata was read from this image.");
+        }
+        col.fixed(&metadata, ROW_H);
+
+        Frame::default();
+
+        let mut row = Flex::default().row();
+            Frame::default();
+            let mut btn_save = Button::default().with_label("Save...");
+            let mut btn_cancel = Button::default().with_label("Cancel");
+            row.fixed(&btn_save, 80);
+            row.fixed(&btn_cancel, 80);
+        row.end();
+        col.fixed(&row, ROW_H);
+    col.end();
+    win.end();
+    win.make_modal(true);
+
+    let mut update_fields = {
+        let mut quality = quality.clone();
+        let mut compression = compression.clone();
+        let mut width = width.clone();
+        let mut height = height.clone();
+        let format = format.clone();
+        let resize = resize.clone();
+        move || {
+            if format.value() == 0 {
+                quality.deactivate();
+                compression.activate();
+            } else {
+                quality.activate();
+                compression.deactivate();
+            }
+            if resize.is_checked() {
+                width.activate();
+                height.activate();
+            } else {
+                width.deactivate();
+                height.deactivate();
+            }
+        }
+    };
+    update_fields();
+    format.set_callback({
+        let mut update_fields = update_fields.clone();
+        move |_| update_fields()
+    });
+    resize.set_callback(move |_| update_fields());
+
+    let accepted = Rc::new(Cell::new(false));
+    btn_save.set_callback({
+        let accepted = accepted.clone();
+        let mut win = win.clone();
+        let (resize, width, height) = (resize.clone(), width.clone(), height.clone());
+        move |_| {
+            if resize.is_checked() && parse_size(&width, &height).is_none() {
+                alert(screen_center().0 - 150, screen_center().1 - 50, "Width and height must be whole numbers above 0.");
+                return;
+            }
+            accepted.set(true);
+            win.hide();
+        }
+    });
+    btn_cancel.set_callback({
+        let mut win = win.clone();
+        move |_| win.hide()
+    });
+
+    win.show();
+    while win.shown() {
+        app::wait();
+    }
+    if !accepted.get() {
+        return None;
+    }
+
+    let format = ExportFormat::ALL.get(format.value().max(0) as usize).copied().unwrap_or(ExportFormat::Png);
+    let mut options = ExportOptions::new(format);
+    options.quality = quality.value() as u8;
+    options.png_compression = match compression.value() {
+        0 => PngCompression::Fast,
+        2 => PngCompression::Best,
+        _ => PngCompression::Default,
+    };
+    if resize.is_checked() {
+        options.resize = parse_size(&width, &height);
+    }
+    options.keep_metadata = metadata.is_checked();
+    Some(options)
+}
+
+///a row with a fixed width label in front of the widget added next
+fn labeled_row(col: &mut Flex, label: &str) -> Flex {
+    let mut row = Flex::default().row();
+    let frame = Frame::default().with_label(label).with_align(fltk::enums::Align::Left | fltk::enums::Align::Inside);
+    row.fixed(&frame, LABEL_W);
+    col.fixed(&row, ROW_H);
+    row
+}
+
+fn parse_size(width: &IntInput, height: &IntInput) -> Option<(u32, u32)> {
+    let w = width.value().trim().parse::<u32>().ok().filter(|&w| w > 0)?;
+    let h = height.value().trim().parse::<u32>().ok().filter(|&h| h > 0)?;
+    Some((w, h))
+}
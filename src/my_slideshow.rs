@@ -0,0 +1,139 @@
+const DEFAULT_INTERVAL: f64 = 5.;
+const MIN_INTERVAL: f64 = 1.;
+const MAX_INTERVAL: f64 = 60.;
+
+use std::{path::PathBuf, time::{SystemTime, UNIX_EPOCH}};
+
+use fltk::app::{self, Sender, TimeoutHandle};
+
+use crate::Message;
+
+pub struct Slideshow {
+    tx: Sender<Message>,
+    pub interval: f64, //seconds
+    pub shuffle: bool,
+    pub looping: bool,
+    pub pause_on_input: bool,
+    pub crossfade: bool,
+    running: bool,
+    timeout: Option<TimeoutHandle>, //none while paused
+    order: Vec<PathBuf>, //shuffled images
+}
+
+impl Slideshow {
+    pub fn new(tx: Sender<Message>) -> Self {
+        Self {
+            tx,
+            interval: DEFAULT_INTERVAL,
+            shuffle: false,
+            looping: true,
+            pause_on_input: true,
+            crossfade: false,
+            running: false,
+            timeout: None,
+            order: Vec::new(),
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.running && self.timeout.is_none()
+    }
+
+    pub fn start(&mut self, images: Vec<PathBuf>, current: Option<&PathBuf>) {
+        self.set_images(images, current);
+        self.running = true;
+        self.arm();
+    }
+
+    ///call again when shuffle is toggled or the listing changes, a shuffled order starts at the current image so none are skipped
+    pub fn set_images(&mut self, images: Vec<PathBuf>, current: Option<&PathBuf>) {
+        self.order = images;
+        if self.shuffle {
+            shuffle(&mut self.order);
+            if let Some(pos) = current.and_then(|current| self.order.iter().position(|pb| pb == current)) {
+                self.order.swap(0, pos);
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        self.disarm();
+        self.running = false;
+        self.order.clear();
+    }
+
+    pub fn pause(&mut self) {
+        self.disarm();
+    }
+
+    pub fn resume(&mut self) {
+        if self.running {
+            self.arm();
+        }
+    }
+
+    ///waits one interval and sends a tick, call again after each tick
+    pub fn arm(&mut self) {
+        self.disarm();
+        let tx = self.tx;
+        self.timeout = Some(app::add_timeout3(self.interval, move |_| {
+            tx.send(Message::SlideshowTick);
+        }));
+    }
+
+    fn disarm(&mut self) {
+        if let Some(handle) = self.timeout.take() {
+            if app::has_timeout3(handle) {
+                app::remove_timeout3(handle);
+            }
+        }
+    }
+
+    pub fn faster(&mut self) {
+        self.set_interval(self.interval - 1.);
+    }
+
+    pub fn slower(&mut self) {
+        self.set_interval(self.interval + 1.);
+    }
+
+    pub fn set_interval(&mut self, interval: f64) {
+        self.interval = interval.clamp(MIN_INTERVAL, MAX_INTERVAL);
+        if self.running && !self.is_paused() { //restart the wait with the new interval
+            self.arm();
+        }
+    }
+
+    ///next image in the shuffled order, wraps around when looping, starts over if the current image isn't in it
+    pub fn next_shuffled(&self, current: &PathBuf) -> Option<PathBuf> {
+        let Some(pos) = self.order.iter().position(|pb| pb == current) else { return self.order.first().cloned() };
+        if pos + 1 < self.order.len() {
+            Some(self.order[pos + 1].clone())
+        } else if self.looping {
+            self.order.first().cloned()
+        } else {
+            None
+        }
+    }
+}
+
+///fisher-yates with a xorshift seeded from the clock, good enough for picking slides
+fn shuffle(list: &mut [PathBuf]) {
+    let mut seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0x2545F4914F6CDD1D)
+        | 1;
+
+    for i in (1..list.len()).rev() {
+        seed ^= seed << 13;
+        seed ^= seed >> 7;
+        seed ^= seed << 17;
+        let j = (seed % (i as u64 + 1)) as usize;
+        list.swap(i, j);
+    }
+}
@@ -0,0 +1,198 @@
+pub const TAG_NEW_SUBFILE_TYPE: u16 = 0xFE;
+pub const TAG_IMAGE_WIDTH: u16 = 0x100;
+pub const TAG_IMAGE_HEIGHT: u16 = 0x101;
+pub const TAG_BITS_PER_SAMPLE: u16 = 0x102;
+pub const TAG_COMPRESSION: u16 = 0x103;
+pub const TAG_PHOTOMETRIC: u16 = 0x106;
+pub const TAG_MAKE: u16 = 0x10F;
+pub const TAG_MODEL: u16 = 0x110;
+pub const TAG_STRIP_OFFSETS: u16 = 0x111;
+pub const TAG_ORIENTATION: u16 = 0x112;
+pub const TAG_SAMPLES_PER_PIXEL: u16 = 0x115;
+pub const TAG_STRIP_BYTE_COUNTS: u16 = 0x117;
+pub const TAG_DATE_TIME: u16 = 0x132;
+pub const TAG_SUB_IFDS: u16 = 0x14A;
+pub const TAG_JPEG_OFFSET: u16 = 0x201;
+pub const TAG_JPEG_LENGTH: u16 = 0x202;
+pub const TAG_EXIF_IFD: u16 = 0x8769;
+pub const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+const MAX_IFDS: usize = 64; //guards against offset loops in broken files
+
+///one field of an image file directory
+#[derive(Clone, Copy)]
+pub struct Entry {
+    pub tag: u16,
+    kind: u16,
+    count: u32,
+    value_pos: usize, //where the value or the offset to it is stored, from the start of the data
+}
+
+///reads the tiff structure used by exif and most camera raw formats, offsets are relative to base
+pub struct Tiff<'a> {
+    data: &'a [u8],
+    base: usize,
+    little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+    ///the byte order mark is checked, the magic number isn't because raw formats change it
+    pub fn new(data: &'a [u8], base: usize) -> Option<Self> {
+        let little_endian = match data.get(base..base + 2)? {
+            b"II" => true,
+            b"MM" => false,
+            _ => return None,
+        };
+        Some(Self { data, base, little_endian })
+    }
+
+    pub fn u16_at(&self, pos: usize) -> Option<u16> {
+        let b = self.data.get(pos..pos + 2)?;
+        Some(if self.little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) })
+    }
+
+    ///a sample in the file's byte order, for image data copied out of the file
+    pub fn u16_of(&self, b: &[u8]) -> u16 {
+        if self.little_endian { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) }
+    }
+
+    pub fn u32_at(&self, pos: usize) -> Option<u32> {
+        let b = self.data.get(pos..pos + 4)?;
+        Some(if self.little_endian { u32::from_le_bytes([b[0], b[1], b[2], b[3]]) } else { u32::from_be_bytes([b[0], b[1], b[2], b[3]]) })
+    }
+
+    ///position of the first directory, from the start of the data
+    pub fn first_ifd(&self) -> Option<usize> {
+        Some(self.base + self.u32_at(self.base + 4)? as usize)
+    }
+
+    ///returns the entries and the position of the next directory in the chain
+    pub fn read_ifd(&self, pos: usize) -> Option<(Vec<Entry>, Option<usize>)> {
+        let count = self.u16_at(pos)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for n in 0..count {
+            let entry_pos = pos + 2 + n * 12;
+            entries.push(Entry {
+                tag: self.u16_at(entry_pos)?,
+                kind: self.u16_at(entry_pos + 2)?,
+                count: self.u32_at(entry_pos + 4)?,
+                value_pos: entry_pos + 8,
+            });
+        }
+        let next = self.u32_at(pos + 2 + count * 12).unwrap_or(0) as usize;
+        let next = if next == 0 { None } else { Some(self.base + next) };
+        Some((entries, next))
+    }
+
+    ///the directory chain and every sub and exif directory below it
+    pub fn all_ifds(&self) -> Vec<Vec<Entry>> {
+        let mut res = Vec::new();
+        let mut todo: Vec<usize> = self.first_ifd().into_iter().collect();
+        let mut seen = Vec::new();
+
+        while let Some(pos) = todo.pop() {
+            if seen.contains(&pos) || seen.len() >= MAX_IFDS {
+                continue;
+            }
+            seen.push(pos);
+
+            if let Some((entries, next)) = self.read_ifd(pos) {
+                for entry in &entries {
+                    if entry.tag == TAG_SUB_IFDS || entry.tag == TAG_EXIF_IFD {
+                        for offset in self.values(entry) {
+                            todo.push(self.base + offset as usize);
+                        }
+                    }
+                }
+                if let Some(next) = next {
+                    todo.push(next);
+                }
+                res.push(entries);
+            }
+        }
+        res
+    }
+
+    fn kind_size(kind: u16) -> usize {
+        match kind {
+            3 | 8 => 2,
+            4 | 9 | 11 | 13 => 4,
+            5 | 10 | 12 => 8,
+            _ => 1,
+        }
+    }
+
+    ///position of the value, small values are stored in the entry itself
+    fn data_pos(&self, entry: &Entry) -> Option<usize> {
+        let size = Tiff::kind_size(entry.kind) * entry.count as usize;
+        if size <= 4 {
+            Some(entry.value_pos)
+        } else {
+            Some(self.base + self.u32_at(entry.value_pos)? as usize)
+        }
+    }
+
+    ///all values of a short or long entry
+    pub fn values(&self, entry: &Entry) -> Vec<u32> {
+        let mut res = Vec::new();
+        if let Some(pos) = self.data_pos(entry) {
+            for n in 0..entry.count as usize {
+                let value = match entry.kind {
+                    3 | 8 => self.u16_at(pos + n * 2).map(|v| v as u32),
+                    4 | 9 | 13 => self.u32_at(pos + n * 4),
+                    1 | 6 | 7 => self.data.get(pos + n).map(|&v| v as u32),
+                    _ => None,
+                };
+                match value {
+                    Some(value) => res.push(value),
+                    None => break,
+                }
+            }
+        }
+        res
+    }
+
+    pub fn value(&self, entry: &Entry) -> Option<u32> {
+        self.values(entry).first().copied()
+    }
+
+    pub fn ascii(&self, entry: &Entry) -> Option<String> {
+        let pos = self.data_pos(entry)?;
+        let bytes = self.data.get(pos..pos + entry.count as usize)?;
+        let text = String::from_utf8_lossy(bytes).trim_end_matches('\0').trim().to_string();
+        if text.is_empty() { None } else { Some(text) }
+    }
+
+    ///the raw bytes of an undefined entry, used for embedded previews
+    pub fn bytes(&self, entry: &Entry) -> Option<&'a [u8]> {
+        let pos = self.data_pos(entry)?;
+        self.data.get(pos..pos + entry.count as usize)
+    }
+
+    ///bytes at an offset relative to base
+    pub fn slice(&self, offset: u32, len: u32) -> Option<&'a [u8]> {
+        let start = self.base + offset as usize;
+        self.data.get(start..start + len as usize)
+    }
+}
+
+pub fn find<'e>(entries: &'e [Entry], tag: u16) -> Option<&'e Entry> {
+    entries.iter().find(|entry| entry.tag == tag)
+}
+
+///start of the tiff structure inside a jpeg's exif segment
+pub fn jpeg_exif_base(jpeg: &[u8]) -> Option<usize> {
+    let mut pos = 2;
+    while pos + 4 <= jpeg.len() && jpeg[pos] == 0xFF {
+        let marker = jpeg[pos + 1];
+        if marker == 0xDA || marker == 0xD9 {
+            break; //image data follows, no more metadata
+        }
+        let len = u16::from_be_bytes([jpeg[pos + 2], jpeg[pos + 3]]) as usize;
+        if marker == 0xE1 && jpeg.get(pos + 4..pos + 10) == Some(b"Exif\0\0") {
+            return Some(pos + 10);
+        }
+        pos += 2 + len;
+    }
+    None
+}
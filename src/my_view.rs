@@ -1,27 +1,37 @@
 use std::collections::HashMap;
 use std::error::Error;
-use std::path::PathBuf;
-use fltk::app::Sender;
+use std::path::{Path, PathBuf};
+use fltk::app::{self, Sender};
 use fltk::button::Button;
-use fltk::dialog::message;
+use fltk::enums::{Event, Key};
+use fltk::dialog::{message, NativeFileChooser, NativeFileChooserOptions, NativeFileChooserType};
 use fltk::frame::Frame;
 use fltk::group::Flex;
-use fltk::prelude::{BrowserExt, GroupExt, InputExt, WidgetBase, WidgetExt, WindowExt};
+use fltk::prelude::{BrowserExt, GroupExt, InputExt, ValuatorExt, WidgetBase, WidgetExt, WindowExt};
 use fltk::group::FlexType::Column;
+use fltk::valuator::{Scrollbar, ScrollbarType};
 use fltk::{input, window};
 
 use fltk_theme::widget_themes::OS_SPACER_THIN_DOWN_BOX;
 use speedy2d::image::ImageHandle;
 
 use crate::my_browser::MyBrowser;
-use crate::my_display::MyDisplay;
-use crate::my_menu::MyMenu;
+use crate::my_export::ExportFormat;
+use crate::my_display::{MipChain, MyDisplay};
+use crate::my_marks::{Mark, MarkFilter};
+use crate::my_menu::{MyMenu, MARK_FILTERS};
 use crate::{Listing, Message, PROGRAM_NAME};
-use crate::ViewSettings;
+use crate::{Orientation, ViewSettings};
+
+const SCROLLBAR_SIZE: i32 = 15;
+const SCROLL_STEPS: i32 = 10000; //scrollbar units for the whole image
+const MARK_TOOLTIP: &str = "Mark of the image, k keeps, x rejects, 1 to 5 rates, 6 to 9 set a color label and t edits tags.";
 
 pub struct MyView {
     main_win: window::Window,
     glut_row: Flex,
+    hscroll: Scrollbar,
+    vscroll: Scrollbar,
     pub status_row: Flex,
     middle_col: Flex,
     pub display: MyDisplay,
@@ -30,9 +40,14 @@ pub struct MyView {
     menu: MyMenu,
     inp_path: input::Input,
     pub want_to_display: Option<PathBuf>,
+    pub partner: Option<PathBuf>, //second page in two page mode, or the image compared against
+    pub crossfade_next: bool, //fade from the current image to the next one displayed
+    pub comic_label: Option<String>, //series, issue and title when inside a comic archive
     vsettings: HashMap<ImageHandle, ViewSettings>, //maybe get vs pr pathbuf and serialize
     stat_display: bool,
     stat_messages: Frame,
+    stat_mark: Frame,
+    stat_pixel: Frame,
     stat_zoomlvl: Frame,
     stat_gpu: Frame,
     stat_images: Frame,
@@ -86,10 +101,38 @@ impl MyView {
 
             let mut glut_row = Flex::default_fill();                
                 glut_row.set_type(Column);
-                let glut_win = window::GlutWindow::default_fill();
-                glut_win.end();
+                let mut viewport_row = Flex::default_fill().row();
+                    let glut_win = window::GlutWindow::default_fill();
+                    glut_win.end();
+                    let mut vscroll = Scrollbar::default();
+                    vscroll.set_type(ScrollbarType::Vertical);
+                    vscroll.set_visible_focus(false); //arrow keys should keep panning the image
+                    viewport_row.fixed(&vscroll, SCROLLBAR_SIZE);
+                viewport_row.end();
+                let mut hscroll = Scrollbar::default();
+                hscroll.set_type(ScrollbarType::Horizontal);
+                hscroll.set_visible_focus(false);
+                glut_row.fixed(&hscroll, SCROLLBAR_SIZE);
             glut_row.end();
-            inp_path.set_value("yay"); //make this input reactive to input
+            inp_path.set_tooltip("Enter goes to the typed folder, archive or image, also inside archives. Tab completes folder and archive names.");
+            inp_path.handle(move |inp, event| {
+                if event == Event::KeyDown {
+                    match app::event_key() {
+                        Key::Enter | Key::KPEnter => {
+                            tx.send(Message::GotoPath(inp.value()));
+                            return true;
+                        },
+
+                        Key::Tab if !app::is_event_shift() => { //shift+tab still moves the focus back
+                            tx.send(Message::CompletePath(inp.value()));
+                            return true; //keeps the focus here
+                        },
+
+                        _ => {},
+                    }
+                }
+                false
+            });
             let mut status_row = Flex::default_fill().row(); //maybe flex is not the way to go about this
                 let mut stat_messages = Frame::default().with_label("");
                 stat_messages.set_align(fltk::enums::Align::Clip);
@@ -98,6 +141,21 @@ impl MyView {
                     spacer.set_frame(OS_SPACER_THIN_DOWN_BOX);
                     status_row.fixed(&spacer, 2);
 
+                let mut stat_mark = Frame::default().with_label("");
+                stat_mark.set_tooltip(MARK_TOOLTIP);
+
+                    let mut spacer = Frame::default();
+                    spacer.set_frame(OS_SPACER_THIN_DOWN_BOX);
+                    status_row.fixed(&spacer, 2);
+
+                let mut stat_pixel = Frame::default().with_label("");
+                stat_pixel.set_align(fltk::enums::Align::Clip);
+                stat_pixel.set_tooltip("Pixel under the mouse, press c to copy its color.");
+
+                    let mut spacer = Frame::default();
+                    spacer.set_frame(OS_SPACER_THIN_DOWN_BOX);
+                    status_row.fixed(&spacer, 2);
+
                 let stat_zoomlvl = Frame::default().with_label("Zoom: 1.23 x 1.23");
                 
                     let mut spacer = Frame::default();
@@ -121,6 +179,8 @@ impl MyView {
                 let mut stat_data = Frame::default().with_label("Data: 1312 MiB");
                 stat_data.set_tooltip("Size of cached data in ram.");
                 
+                status_row.fixed(&stat_mark, 120);
+                status_row.fixed(&stat_pixel, 290);
                 status_row.fixed(&stat_zoomlvl, 150);
                 status_row.fixed(&stat_gpu, 115);
                 status_row.fixed(&stat_images, 135);
@@ -136,6 +196,23 @@ impl MyView {
         main_win.show();
         
         let display = MyDisplay::build(glut_win, tx);
+
+        for (bar, horizontal) in [(&mut hscroll, true), (&mut vscroll, false)] {
+            bar.set_linesize(SCROLL_STEPS / 50);
+            bar.set_callback({
+                let vc = display.s_vc.clone();
+                let mut glut_win = display.glut_win.clone();
+                move |bar| {
+                    let first = bar.value() as f32 / SCROLL_STEPS as f32;
+                    if horizontal {
+                        vc.borrow_mut().scroll_to(Some(first), None);
+                    } else {
+                        vc.borrow_mut().scroll_to(None, Some(first));
+                    }
+                    glut_win.redraw();
+                }
+            });
+        }
         let want_to_display: Option<PathBuf> = None;
         let vsettings: HashMap<ImageHandle, ViewSettings> = HashMap::new();
        
@@ -143,6 +220,8 @@ impl MyView {
             main_win,
             browser_row,
             glut_row,
+            hscroll,
+            vscroll,
             status_row,
             middle_col,
             display,
@@ -150,9 +229,14 @@ impl MyView {
             menu,
             inp_path,
             want_to_display,
+            partner: None,
+            crossfade_next: false,
+            comic_label: None,
             vsettings,
             stat_display: true,
             stat_messages, //make a struct with these?
+            stat_mark,
+            stat_pixel,
             stat_zoomlvl,
             stat_gpu,
             stat_images,
@@ -168,6 +252,20 @@ impl MyView {
         self.stat_messages.set_label(s);
     }
 
+    ///tags are shown as tooltip
+    pub fn set_stat_mark(&mut self, mark: &Mark) {
+        self.stat_mark.set_label(&mark.label());
+        if mark.tags.is_empty() {
+            self.stat_mark.set_tooltip(MARK_TOOLTIP);
+        } else {
+            self.stat_mark.set_tooltip(&format!("Tags: {}", mark.tags_text()));
+        }
+    }
+
+    pub fn set_stat_pixel(&mut self, s: &str) {
+        self.stat_pixel.set_label(s);
+    }
+
     pub fn set_stat_zoomlvl(&mut self, s: &str) {
         self.stat_zoomlvl.set_label(s);
     }
@@ -198,11 +296,60 @@ impl MyView {
         let _ = self.browser.browser.take_focus();
     }
 
-    pub fn populate_browser(&mut self, listing: &Vec<Listing>) { // move this into my_browser?
-        self.browser.populate_browser(listing);
+    ///follows zooming and panning, a scrollbar is inactive when its whole axis is visible
+    pub fn update_scrollbars(&mut self) {
+        let ((x, w), (y, h)) = self.display.s_vc.borrow().visible_range();
+        for (bar, first, size) in [(&mut self.hscroll, x, w), (&mut self.vscroll, y, h)] {
+            let size = ((size * SCROLL_STEPS as f32).round() as i32).clamp(1, SCROLL_STEPS);
+            bar.scroll_value((first * SCROLL_STEPS as f32).round() as i32, size, 0, SCROLL_STEPS);
+            if size < SCROLL_STEPS {
+                bar.activate();
+            } else {
+                bar.deactivate();
+            }
+        }
+    }
+
+    pub fn populate_browser(&mut self, listing: &Vec<Listing>, marks: &HashMap<PathBuf, Mark>) { // move this into my_browser?
+        self.browser.populate_browser(listing, marks);
+    }
+
+    ///checks a radio or toggle item when the setting changes without the menu
+    pub fn check_menu_item(&mut self, path: &str) {
+        if let Some(mut item) = self.menu.menu.find_item(path) {
+            item.setonly();
+        }
+    }
+
+    ///checks the radio item of the filter, or none for a tag search
+    pub fn check_mark_filter(&mut self, filter: &MarkFilter) {
+        for (label, item_filter) in &MARK_FILTERS {
+            if let Some(mut item) = self.menu.menu.find_item(label) {
+                if item_filter == filter { item.set() } else { item.clear() }
+            }
+        }
+    }
+
+    ///1 based like the browser
+    pub fn set_browser_mark(&mut self, line: i32, mark: &Mark) {
+        self.browser.set_mark(line, mark);
+    }
+
+    ///1 based like the browser, none without a selection
+    pub fn selected_browser_item(&self) -> Option<i32> {
+        let nr = self.browser.browser.value();
+        if nr > 0 { Some(nr) } else { None }
+    }
+
+    ///all selected lines, 1 based like the browser
+    pub fn selected_browser_items(&self) -> Vec<i32> {
+        self.browser.browser.selected_items()
     }
 
     pub fn select_browser_item(&mut self, index: i32) {        
+        for line in self.browser.browser.selected_items() { //selecting adds to the selection in a multi browser
+            self.browser.browser.deselect(line);
+        }
         self.browser.browser.select(index +1); //starts from 1
     }
 
@@ -210,11 +357,22 @@ impl MyView {
         self.inp_path.set_value(&pb.display().to_string());
     }
 
-    pub fn display_image(&mut self, handle: ImageHandle) {
+    ///completed text, typing goes on at the end
+    pub fn set_input_completion(&mut self, text: &str) {
+        self.inp_path.set_value(text);
+        let _ = self.inp_path.set_position(text.len() as i32);
+    }
+
+    pub fn display_image(&mut self, chain: MipChain) {
+        if self.crossfade_next {
+            self.crossfade_next = false;
+            self.display.start_crossfade();
+        }
         self.save_viewsettings();
-        self.load_viewsettings(&handle);
+        self.load_viewsettings(chain.base());
+        self.display.s_vc.borrow_mut().clear_selection();
         self.set_displaying_layout();
-        *self.display.s_displaying_image.borrow_mut() = Some(handle);
+        *self.display.s_displaying_image.borrow_mut() = Some(chain);
         self.display.s_vc.borrow_mut().update_view_settings();
         self.update_window_label();
         self.display.glut_win.redraw();
@@ -231,20 +389,25 @@ impl MyView {
                 zoom_lvl_y: 1.,
                 centerpos_x: 0.5,
                 centerpos_y: 0.5,
+                orientation: Orientation::default(),
             });
         }
     }
 
     fn save_viewsettings(&mut self) { //maybe serialize pb and vs
-        if let Some(handle) = self.display.s_displaying_image.borrow().as_ref() {
-            self.vsettings.insert(handle.clone(), self.display.get_vs());
+        if let Some(chain) = self.display.s_displaying_image.borrow().as_ref() {
+            self.vsettings.insert(chain.base().clone(), self.display.get_vs());
         }
     }
 
     pub fn stop_image_display(&mut self) {
         self.save_viewsettings();
         self.want_to_display = None;
+        self.partner = None;
         *self.display.s_displaying_image.borrow_mut() = None;
+        self.display.set_second_image(None);
+        self.display.set_animation(None);
+        self.set_stat_pixel("");
         self.set_browsing_layout();
         self.update_window_label();
     }
@@ -252,7 +415,12 @@ impl MyView {
     pub fn update_window_label(&mut self) {
         if self.display.s_displaying_image.borrow().is_some() {
             if let Some(pb) = &self.want_to_display {
-                let mut temp = pb.display().to_string();
+                let mut temp = String::new();
+                if let Some(comic) = &self.comic_label {
+                    temp += comic;
+                    temp += " - ";
+                }
+                temp += &pb.display().to_string();
                 temp += " - ";
                 temp += PROGRAM_NAME;
                 self.main_win.set_label(&temp);
@@ -262,6 +430,42 @@ impl MyView {
         }
     }
 
+    ///asks where to save, in the nearest real directory of the shown path, none when cancelled
+    pub fn choose_save_path(&self, title: &str, name: &str, near: &PathBuf) -> Option<PathBuf> {
+        let mut chooser = NativeFileChooser::new(NativeFileChooserType::BrowseSaveFile);
+        chooser.set_title(title);
+        chooser.set_filter(&ExportFormat::dialog_filter());
+        chooser.set_preset_file(name);
+        chooser.set_option(NativeFileChooserOptions::SaveAsConfirm);
+        if let Some(dir) = near.ancestors().find(|dir| dir.is_dir()) { //images in archives have virtual paths
+            let _ = chooser.set_directory(&dir);
+        }
+        chooser.show();
+
+        let mut path = chooser.filename();
+        if path.as_os_str().is_empty() {
+            return None;
+        }
+        if ExportFormat::from_path(&path).is_none() { //typed without an extension, use the picked filter
+            let format = ExportFormat::ALL.get(chooser.filter_value().max(0) as usize).copied().unwrap_or(ExportFormat::Png);
+            path.set_extension(format.extension());
+        }
+        Some(path)
+    }
+
+    ///asks for a folder, starting in the given one or the nearest folder above it, none when cancelled
+    pub fn choose_folder(&self, title: &str, start: &Path) -> Option<PathBuf> {
+        let mut chooser = NativeFileChooser::new(NativeFileChooserType::BrowseDir);
+        chooser.set_title(title);
+        if let Some(dir) = start.ancestors().find(|dir| dir.is_dir()) {
+            let _ = chooser.set_directory(&dir);
+        }
+        chooser.show();
+
+        let path = chooser.filename();
+        if path.as_os_str().is_empty() { None } else { Some(path) }
+    }
+
     pub fn toggle_statusbar(&mut self) {
         self.stat_display = !self.stat_display;
         if self.stat_display {
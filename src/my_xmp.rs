@@ -0,0 +1,607 @@
+const ARCHIVE_DB_SUFFIX: &str = ".tags.xmp"; //marks of all images in an archive, next to it
+const PICK_REJECTED: &str = "1"; //digiKam pick labels
+const PICK_ACCEPTED: &str = "3";
+const COLOR_LABELS: [&str; 10] = ["", "Red", "Orange", "Yellow", "Green", "Blue", "Purple", "Gray", "Black", "White"]; //by digiKam color label number, it calls 6 magenta
+const HIERARCHIES: [(&str, char, &str); 2] = [("digiKam:TagsList", '/', "rdf:Seq"), ("lr:hierarchicalSubject", '|', "rdf:Bag")]; //tag paths, their separator and list type
+const NAMESPACES: [(&str, &str); 4] = [
+    ("xmp", "http://ns.adobe.com/xap/1.0/"),
+    ("dc", "http://purl.org/dc/elements/1.1/"),
+    ("digiKam", "http://www.digikam.org/ns/1.0/"),
+    ("lr", "http://ns.adobe.com/lightroom/1.0/"),
+];
+//the ones written, everything else is left alone
+const PROPERTIES: [&str; 7] = ["xmp:Rating", "xmp:Label", "digiKam:PickLabel", "digiKam:ColorLabel", "dc:subject", "digiKam:TagsList", "lr:hierarchicalSubject"];
+const TEMPLATE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""/>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+
+use std::{error::Error, fs::{self, File}, io::Write, path::{Path, PathBuf}};
+
+use crate::my_marks::{Flag, Mark};
+
+///name.ext.xmp as darktable and digiKam write it, or name.xmp when only that exists
+pub fn sidecar_path(image: &Path) -> PathBuf {
+    let mut full = image.as_os_str().to_owned();
+    full.push(".xmp");
+    let full = PathBuf::from(full);
+    let short = image.with_extension("xmp");
+    if !full.exists() && short.exists() { short } else { full }
+}
+
+pub fn archive_db_path(archive: &Path) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(ARCHIVE_DB_SUFFIX);
+    PathBuf::from(path)
+}
+
+///none without a sidecar
+pub fn read_sidecar(image: &Path) -> Option<Mark> {
+    let xml = fs::read_to_string(sidecar_path(image)).ok()?;
+    Some(parse_mark(&xml))
+}
+
+///updates our properties in the sidecar, so what other programs wrote to it is kept
+pub fn write_sidecar(image: &Path, mark: &Mark) -> Result<(), Box<dyn Error>> {
+    let path = sidecar_path(image);
+    let existing = fs::read_to_string(&path).ok();
+    if existing.is_none() && mark.is_empty() {
+        return Ok(());
+    }
+    write_replacing(&path, &update_xmp(existing.as_deref(), mark))
+}
+
+///the sidecar follows its image when it is moved or renamed
+pub fn move_sidecar(from: &Path, to: &Path) -> Result<(), Box<dyn Error>> {
+    let old = sidecar_path(from);
+    let new = sidecar_path(to);
+    if old.exists() && !new.exists() {
+        fs::rename(old, new)?;
+    }
+    Ok(())
+}
+
+///marks by path inside the archive
+pub fn read_archive_db(archive: &Path) -> Vec<(PathBuf, Mark)> {
+    let Ok(xml) = fs::read_to_string(archive_db_path(archive)) else { return Vec::new() };
+    descriptions(&xml)
+        .filter_map(|(tag, block)| {
+            let about = unescape(attribute(tag, "rdf:about")?);
+            Some((PathBuf::from(about), parse_mark(block)))
+        })
+        .collect()
+}
+
+///one description pr marked image, the file is removed when no image has a mark
+pub fn write_archive_db(archive: &Path, entries: &[(PathBuf, &Mark)]) -> Result<(), Box<dyn Error>> {
+    let path = archive_db_path(archive);
+    let entries: Vec<_> = entries.iter().filter(|(_, mark)| !mark.is_empty()).collect();
+    if entries.is_empty() {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<x:xmpmeta xmlns:x=\"adobe:ns:meta/\">\n <rdf:RDF xmlns:rdf=\"http://www.w3.org/1999/02/22-rdf-syntax-ns#\"");
+    for (prefix, uri) in NAMESPACES {
+        xml += &format!("\n    xmlns:{prefix}=\"{uri}\"");
+    }
+    xml += ">\n";
+    for (inner, mark) in entries {
+        xml += &format!("  <rdf:Description rdf:about=\"{}\"{}>\n", escape(&inner.to_string_lossy()), mark_attributes(mark));
+        xml += &tag_elements(mark, "");
+        xml += "  </rdf:Description>\n";
+    }
+    xml += " </rdf:RDF>\n</x:xmpmeta>\n";
+    write_replacing(&path, &xml)
+}
+
+///written next to it first, so a crash or a full disk can't leave half of darktable's edit history
+fn write_replacing(path: &Path, text: &str) -> Result<(), Box<dyn Error>> {
+    let name = path.file_name().ok_or("No file name")?.to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.writing"));
+    let res = File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(text.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if res.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    Ok(res?)
+}
+
+///rating, label and tags from an xmp packet embedded in the image file
+pub fn embedded_mark(data: &[u8]) -> Option<Mark> {
+    let start = find(data, b"<x:xmpmeta")?;
+    let end = find(&data[start..], b"</x:xmpmeta>")? + start;
+    let mark = parse_mark(&String::from_utf8_lossy(&data[start..end]));
+    if mark.is_empty() { None } else { Some(mark) }
+}
+
+///reject is a rating of -1 for darktable and lightroom, digiKam also has pick labels and numbered color labels
+fn parse_mark(xml: &str) -> Mark {
+    let mut mark = Mark::default();
+    match property(xml, "xmp:Rating").and_then(|rating| rating.trim().parse::<f32>().ok()) {
+        Some(rating) if rating < 0. => mark.flag = Flag::Reject,
+        Some(rating) => mark.stars = rating.round().min(5.) as u8,
+        None => {},
+    }
+    match property(xml, "digiKam:PickLabel").as_deref().map(str::trim) {
+        Some(PICK_REJECTED) => mark.flag = Flag::Reject,
+        Some(PICK_ACCEPTED) => mark.flag = Flag::Keep,
+        _ => {},
+    }
+    mark.label = property(xml, "xmp:Label").filter(|label| !label.is_empty()).or_else(|| {
+        let number: usize = property(xml, "digiKam:ColorLabel")?.trim().parse().ok()?;
+        COLOR_LABELS.get(number).filter(|label| !label.is_empty()).map(|label| label.to_string())
+    });
+    mark.tags = list_items(xml, "dc:subject");
+    if mark.tags.is_empty() { //the last part of the paths when only those are there
+        for (name, separator, _) in HIERARCHIES {
+            for path in list_items(xml, name) {
+                let leaf = path.rsplit(separator).next().unwrap_or_default().to_string();
+                if !leaf.is_empty() && !mark.tags.contains(&leaf) {
+                    mark.tags.push(leaf);
+                }
+            }
+        }
+    }
+    mark
+}
+
+///our properties are taken out of the packet and added again to the first description
+fn update_xmp(existing: Option<&str>, mark: &Mark) -> String {
+    let existing = existing.filter(|xml| xml.contains("<rdf:Description")).unwrap_or(TEMPLATE);
+    let mut xml = existing.to_string();
+    for name in PROPERTIES {
+        xml = remove_element(&xml, name);
+    }
+
+    //attributes can be on any description
+    let mut res = String::with_capacity(xml.len() + 512);
+    let mut rest = xml.as_str();
+    let mut first = true;
+    while let Some(start) = rest.find("<rdf:Description") {
+        let Some(end) = tag_end(rest, start) else { break };
+        res.push_str(&rest[..start]);
+        let mut tag = rest[start..end].to_string();
+        for name in PROPERTIES {
+            tag = remove_attribute(&tag, name);
+        }
+        rest = &rest[end..];
+
+        if first {
+            first = false;
+            let self_closing = tag.ends_with("/>");
+            let mut open = tag.trim_end_matches('>').trim_end_matches('/').trim_end().to_string();
+            let in_scope = format!("{res}{open}"); //declarations on the description and its parents
+            for (prefix, uri) in NAMESPACES {
+                if !in_scope.contains(&format!("xmlns:{prefix}=")) {
+                    open += &format!("\n    xmlns:{prefix}=\"{uri}\"");
+                }
+            }
+            open += &mark_attributes(mark);
+            res.push_str(&open);
+            res.push('>');
+            let tags = tag_elements(mark, existing);
+            if !tags.is_empty() {
+                res.push('\n');
+                res.push_str(tags.trim_end());
+            }
+            if self_closing {
+                res.push('\n');
+                res.push_str("  </rdf:Description>");
+            }
+        } else {
+            res.push_str(&tag);
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
+fn mark_attributes(mark: &Mark) -> String {
+    let mut res = String::new();
+    let rating = if mark.flag == Flag::Reject { -1 } else { mark.stars as i32 };
+    if rating != 0 {
+        res += &format!("\n    xmp:Rating=\"{rating}\"");
+    }
+    if let Some(label) = &mark.label {
+        res += &format!("\n    xmp:Label=\"{}\"", escape(label));
+    }
+    match mark.flag {
+        Flag::Keep => res += &format!("\n    digiKam:PickLabel=\"{PICK_ACCEPTED}\""),
+        Flag::Reject => res += &format!("\n    digiKam:PickLabel=\"{PICK_REJECTED}\""),
+        Flag::None => {},
+    }
+    if let Some(number) = mark.label.as_deref().and_then(color_label_number) {
+        res += &format!("\n    digiKam:ColorLabel=\"{number}\"");
+    }
+    res
+}
+
+///other labels only go in xmp:Label
+fn color_label_number(label: &str) -> Option<usize> {
+    if label.eq_ignore_ascii_case("Magenta") {
+        return Some(6);
+    }
+    COLOR_LABELS.iter().position(|name| !name.is_empty() && name.eq_ignore_ascii_case(label))
+}
+
+///dc:subject and the tag paths digiKam and darktable read, paths already in the packet are kept for tags that stay
+fn tag_elements(mark: &Mark, existing: &str) -> String {
+    if mark.tags.is_empty() {
+        return String::new();
+    }
+    let mut res = list_element("dc:subject", "rdf:Bag", &mark.tags);
+    for (name, separator, list_type) in HIERARCHIES {
+        let old_paths = list_items(existing, name);
+        let mut paths = Vec::new();
+        for tag in &mark.tags {
+            let kept: Vec<&String> = old_paths.iter().filter(|path| path.rsplit(separator).next() == Some(tag.as_str())).collect();
+            if kept.is_empty() {
+                paths.push(tag.clone());
+            } else {
+                paths.extend(kept.into_iter().cloned());
+            }
+        }
+        res += &list_element(name, list_type, &paths);
+    }
+    res
+}
+
+fn list_element(name: &str, list_type: &str, items: &[String]) -> String {
+    let mut res = format!("   <{name}>\n    <{list_type}>\n");
+    for item in items {
+        res += &format!("     <rdf:li>{}</rdf:li>\n", escape(item));
+    }
+    res += &format!("    </{list_type}>\n   </{name}>\n");
+    res
+}
+
+///start tag and content of every description
+fn descriptions(xml: &str) -> impl Iterator<Item = (&str, &str)> {
+    let mut rest = xml;
+    std::iter::from_fn(move || {
+        let start = rest.find("<rdf:Description")?;
+        let end = tag_end(rest, start)?;
+        let tag = &rest[start..end];
+        let block = if tag.ends_with("/>") {
+            tag
+        } else {
+            let close = rest[end..].find("</rdf:Description>").map(|pos| end + pos).unwrap_or(rest.len());
+            &rest[start..close]
+        };
+        rest = &rest[start + block.len()..];
+        Some((tag, block))
+    })
+}
+
+///attribute of a description, or the text of an element of that name
+fn property(xml: &str, name: &str) -> Option<String> {
+    descriptions(xml)
+        .find_map(|(tag, _)| attribute(tag, name))
+        .map(unescape)
+        .or_else(|| element_text(xml, name))
+}
+
+///the rdf:li texts of a bag or sequence
+fn list_items(xml: &str, name: &str) -> Vec<String> {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    let Some(start) = xml.find(&open).map(|pos| pos + open.len()) else { return Vec::new() };
+    let end = xml[start..].find(&close).map(|pos| pos + start).unwrap_or(xml.len());
+
+    let mut items = Vec::new();
+    let mut rest = &xml[start..end];
+    while let Some(li) = rest.find("<rdf:li") {
+        let Some(text_start) = tag_end(rest, li) else { break };
+        let Some(text_end) = rest[text_start..].find("</rdf:li>").map(|pos| pos + text_start) else { break };
+        let item = unescape(rest[text_start..text_end].trim());
+        if !item.is_empty() {
+            items.push(item);
+        }
+        rest = &rest[text_end..];
+    }
+    items
+}
+
+fn element_text(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{name}>");
+    let close = format!("</{name}>");
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&close)? + start;
+    Some(unescape(xml[start..end].trim())).filter(|text| !text.is_empty())
+}
+
+///removes <name>..</name> and <name/> with the indentation in front of them
+fn remove_element(xml: &str, name: &str) -> String {
+    let mut res = String::with_capacity(xml.len());
+    let mut rest = xml;
+    let open = format!("<{name}");
+    let close = format!("</{name}>");
+    while let Some(start) = rest.find(&open) {
+        let after_name = &rest[start + open.len()..];
+        if !after_name.starts_with(['>', '/', ' ', '\t', '\r', '\n']) { //a longer name
+            res.push_str(&rest[..start + open.len()]);
+            rest = after_name;
+            continue;
+        }
+        let Some(end) = tag_end(rest, start) else { break };
+        let end = if rest[..end].ends_with("/>") {
+            end
+        } else {
+            rest[end..].find(&close).map(|pos| end + pos + close.len()).unwrap_or(rest.len())
+        };
+        res.push_str(rest[..start].trim_end_matches([' ', '\t']));
+        rest = rest[end..].strip_prefix('\n').unwrap_or(&rest[end..]);
+    }
+    res.push_str(rest);
+    res
+}
+
+///removes name="..." with the whitespace in front of it
+fn remove_attribute(tag: &str, name: &str) -> String {
+    for quote in ['"', '\''] {
+        let pattern = format!("{name}={quote}");
+        if let Some(pos) = tag.find(&pattern).filter(|&pos| pos > 0 && tag[..pos].ends_with(char::is_whitespace)) {
+            let value_start = pos + pattern.len();
+            if let Some(value_end) = tag[value_start..].find(quote).map(|end| value_start + end + 1) {
+                return format!("{}{}", tag[..pos].trim_end(), &tag[value_end..]);
+            }
+        }
+    }
+    tag.to_string()
+}
+
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        let pattern = format!("{name}={quote}");
+        let mut search_from = 0;
+        while let Some(found) = tag[search_from..].find(&pattern) {
+            let pos = search_from + found;
+            //don't match the end of a longer attribute name
+            if pos > 0 && tag[..pos].ends_with(char::is_whitespace) {
+                let start = pos + pattern.len();
+                let end = tag[start..].find(quote)? + start;
+                return Some(&tag[start..end]);
+            }
+            search_from = pos + pattern.len();
+        }
+    }
+    None
+}
+
+///index after the > closing the tag starting at start, quoted values may contain >
+fn tag_end(xml: &str, start: usize) -> Option<usize> {
+    let mut quote = None;
+    for (pos, ch) in xml[start..].char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') => quote = Some(ch),
+            (Some(q), _) if ch == q => quote = None,
+            (None, '>') => return Some(start + pos + 1),
+            _ => {},
+        }
+    }
+    None
+}
+
+fn find(data: &[u8], needle: &[u8]) -> Option<usize> {
+    data.windows(needle.len()).position(|window| window == needle)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //written by darktable 4.6 for a raw file, with history it doesn't share with other programs
+    const DARKTABLE: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="XMP Core 4.4.0-Exiv2">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:exif="http://ns.adobe.com/exif/1.0/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:xmpMM="http://ns.adobe.com/xap/1.0/mm/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+    xmlns:lr="http://ns.adobe.com/lightroom/1.0/"
+    xmlns:darktable="http://darktable.sf.net/"
+   exif:DateTimeOriginal="2024:05:17 14:03:22"
+   xmp:Rating="3"
+   xmp:Label="Red"
+   xmpMM:DerivedFrom="IMG_1234.CR3"
+   darktable:import_timestamp="63851234567000000"
+   darktable:change_timestamp="-1"
+   darktable:xmp_version="5"
+   darktable:raw_params="0"
+   darktable:auto_presets_applied="1"
+   darktable:history_end="1"
+   darktable:iop_order_version="4">
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>beach</rdf:li>
+     <rdf:li>family</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+   <lr:hierarchicalSubject>
+    <rdf:Bag>
+     <rdf:li>places|beach</rdf:li>
+    </rdf:Bag>
+   </lr:hierarchicalSubject>
+   <darktable:masks_history>
+    <rdf:Seq/>
+   </darktable:masks_history>
+   <darktable:history>
+    <rdf:Seq>
+     <rdf:li
+      darktable:num="0"
+      darktable:operation="exposure"
+      darktable:enabled="1"
+      darktable:modversion="6"
+      darktable:params="00000000000080b9"
+      darktable:multi_name=""
+      darktable:multi_priority="0"
+      darktable:blendop_version="13"
+      darktable:blendop_params="gz11eJxjYIAACQYYOOHEgAZY0QVwggZ7CB6pfOygYtoAAK4AB0M="/>
+    </rdf:Seq>
+   </darktable:history>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+"#;
+
+    //written by digiKam 8 through exiv2, with its own tag tree and a windows rating
+    const DIGIKAM: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/" x:xmptk="XMP Core 4.4.0-Exiv2">
+ <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+  <rdf:Description rdf:about=""
+    xmlns:digiKam="http://www.digikam.org/ns/1.0/"
+    xmlns:xmp="http://ns.adobe.com/xap/1.0/"
+    xmlns:MicrosoftPhoto="http://ns.microsoft.com/photo/1.0/"
+    xmlns:dc="http://purl.org/dc/elements/1.1/"
+   digiKam:PickLabel="3"
+   digiKam:ColorLabel="0"
+   xmp:Rating="4"
+   MicrosoftPhoto:Rating="75">
+   <digiKam:TagsList>
+    <rdf:Seq>
+     <rdf:li>People/Anna</rdf:li>
+    </rdf:Seq>
+   </digiKam:TagsList>
+   <dc:subject>
+    <rdf:Bag>
+     <rdf:li>Anna</rdf:li>
+    </rdf:Bag>
+   </dc:subject>
+  </rdf:Description>
+ </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    fn mark(flag: Flag, stars: u8, label: Option<&str>, tags: &[&str]) -> Mark {
+        Mark { flag, stars, label: label.map(str::to_string), tags: tags.iter().map(|tag| tag.to_string()).collect() }
+    }
+
+    #[test]
+    fn parses_darktable() {
+        assert_eq!(parse_mark(DARKTABLE), mark(Flag::None, 3, Some("Red"), &["beach", "family"]));
+    }
+
+    #[test]
+    fn parses_digikam() {
+        //MicrosoftPhoto:Rating isn't taken for xmp:Rating
+        assert_eq!(parse_mark(DIGIKAM), mark(Flag::Keep, 4, None, &["Anna"]));
+    }
+
+    #[test]
+    fn parses_rejects_and_elements() {
+        assert_eq!(parse_mark(&DARKTABLE.replace("xmp:Rating=\"3\"", "xmp:Rating=\"-1\"")).flag, Flag::Reject);
+        assert_eq!(parse_mark(&DIGIKAM.replace("digiKam:PickLabel=\"3\"", "digiKam:PickLabel=\"1\"")).flag, Flag::Reject);
+
+        let xml = TEMPLATE.replace("<rdf:Description rdf:about=\"\"/>", "<rdf:Description rdf:about=\"\">\n   <xmp:Rating>2</xmp:Rating>\n   <xmp:Label>Blue</xmp:Label>\n  </rdf:Description>");
+        assert_eq!(parse_mark(&xml), mark(Flag::None, 2, Some("Blue"), &[]));
+    }
+
+    #[test]
+    fn darktable_round_trip_keeps_the_rest() {
+        let new = mark(Flag::Keep, 5, Some("Green"), &["beach", "sea", "rock & roll"]);
+        let xml = update_xmp(Some(DARKTABLE), &new);
+        assert_eq!(parse_mark(&xml), new);
+
+        for kept in [
+            "exif:DateTimeOriginal=\"2024:05:17 14:03:22\"",
+            "xmpMM:DerivedFrom=\"IMG_1234.CR3\"",
+            "darktable:history_end=\"1\"",
+            "<rdf:li>places|beach</rdf:li>", //the path of a tag that stays
+            "<rdf:Seq/>",
+            "darktable:operation=\"exposure\"",
+            "darktable:blendop_params=\"gz11eJxjYIAACQYYOOHEgAZY0QVwggZ7CB6pfOygYtoAAK4AB0M=\"/>",
+        ] {
+            assert!(xml.contains(kept), "lost {kept}");
+        }
+        assert!(!xml.contains("family"), "old tag left");
+        assert!(xml.contains("digiKam:ColorLabel=\"4\""));
+        assert_eq!(xml.matches("<rdf:li>sea</rdf:li>").count(), 3); //dc:subject, digiKam:TagsList and lr:hierarchicalSubject
+        assert_eq!(xml.matches("<rdf:li>rock &amp; roll</rdf:li>").count(), 3);
+        assert_eq!(xml.matches("xmlns:xmp=").count(), 1);
+        assert_eq!(xml.matches("xmlns:digiKam=").count(), 1);
+        assert_eq!(xml.matches("xmlns:lr=").count(), 1);
+        assert_eq!(xml.matches("<dc:subject>").count(), 1);
+        assert_eq!(xml.matches("<lr:hierarchicalSubject>").count(), 1);
+        assert_eq!(xml.matches("<rdf:Description").count(), xml.matches("</rdf:Description>").count());
+
+        //writing the same mark again changes nothing
+        assert_eq!(update_xmp(Some(&xml), &new), xml);
+    }
+
+    #[test]
+    fn digikam_edit_keeps_tag_paths() {
+        let new = mark(Flag::Keep, 4, Some("Blue"), &["Anna", "Ben"]);
+        let xml = update_xmp(Some(DIGIKAM), &new);
+        assert_eq!(parse_mark(&xml), new);
+        assert!(xml.contains("<digiKam:TagsList>\n    <rdf:Seq>\n     <rdf:li>People/Anna</rdf:li>\n     <rdf:li>Ben</rdf:li>\n    </rdf:Seq>"));
+        assert!(xml.contains("digiKam:ColorLabel=\"5\""));
+        assert!(!xml.contains("digiKam:ColorLabel=\"0\""));
+        assert_eq!(xml.matches("<digiKam:TagsList>").count(), 1);
+    }
+
+    #[test]
+    fn digikam_clearing_removes_tags_and_labels() {
+        let xml = update_xmp(Some(DIGIKAM), &Mark::default());
+        assert_eq!(parse_mark(&xml), Mark::default());
+        for kept in ["MicrosoftPhoto:Rating=\"75\"", "<?xpacket end=\"w\"?>"] {
+            assert!(xml.contains(kept), "lost {kept}");
+        }
+        for gone in ["xmp:Rating=", "digiKam:PickLabel=", "digiKam:ColorLabel=", "<dc:subject>", "<digiKam:TagsList>", "People/Anna"] {
+            assert!(!xml.contains(gone), "left {gone}");
+        }
+    }
+
+    #[test]
+    fn reads_digikam_labels_and_paths() {
+        let xml = DIGIKAM.replace("digiKam:ColorLabel=\"0\"", "digiKam:ColorLabel=\"6\"");
+        assert_eq!(parse_mark(&xml).label.as_deref(), Some("Purple"));
+        //only the paths, as some programs write them
+        let xml = remove_element(DIGIKAM, "dc:subject");
+        assert_eq!(parse_mark(&xml).tags, vec!["Anna".to_string()]);
+    }
+
+    #[test]
+    fn new_sidecar_from_template() {
+        let new = mark(Flag::Reject, 0, Some("Purple"), &["a<b"]);
+        let xml = update_xmp(None, &new);
+        assert_eq!(parse_mark(&xml), new);
+        assert!(xml.contains("xmp:Rating=\"-1\""));
+        assert!(xml.contains("<rdf:li>a&lt;b</rdf:li>"));
+    }
+
+    #[test]
+    fn removes_elements() {
+        assert_eq!(remove_element("a\n   <dc:subject>\n    <rdf:Bag/>\n   </dc:subject>\nb", "dc:subject"), "a\nb");
+        assert_eq!(remove_element("a\n   <xmp:Label/>\nb", "xmp:Label"), "a\nb");
+        assert_eq!(remove_element("<x a=\"1>2\">\n <xmp:Label>Red</xmp:Label>\n</x>", "xmp:Label"), "<x a=\"1>2\">\n</x>");
+        //longer names and other namespaces stay
+        let other = "<xmp:RatingCount>2</xmp:RatingCount>\n<MicrosoftPhoto:Rating>75</MicrosoftPhoto:Rating>";
+        assert_eq!(remove_element(other, "xmp:Rating"), other);
+    }
+}